@@ -0,0 +1,256 @@
+use crate::{provision::Tool, shell::Shell};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// An environment variable a tool needs when the environment is activated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVar {
+    pub name: String,
+    /// The value, as a path relative to the environment root.
+    pub path: String,
+    /// Only set the variable if the path exists at activation time.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub if_exists: bool,
+}
+
+impl EnvVar {
+    /// A variable pointing at `path` inside the environment.
+    pub fn path(name: &str, path: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            path: path.to_string(),
+            if_exists: false,
+        }
+    }
+
+    /// Leaves the variable unset when its path does not exist, e.g. so that a
+    /// system-provided tool can fall back to its own defaults.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+}
+
+/// Everything a single installed tool contributes to the activated environment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Activation {
+    /// The name of the tool.
+    #[serde(default)]
+    pub tool: String,
+    /// Code run when an interactive shell of the environment's shell starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell_init: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_vars: Vec<EnvVar>,
+    /// Directories, relative to the environment root, prepended to `PATH` after `bin`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_dirs: Vec<String>,
+    /// Directories, relative to the environment root, prepended to `XDG_DATA_DIRS`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_dirs: Vec<String>,
+}
+
+impl Activation {
+    /// Collects the activation contributions of `tool` to an environment running `shell`.
+    pub fn of<T: Tool + ?Sized>(tool: &T, shell: Shell) -> Self {
+        Self {
+            tool: tool.name().to_string(),
+            shell_init: tool.shell_init(shell),
+            env_vars: tool.env_vars(),
+            path_dirs: tool.path_dirs(),
+            data_dirs: tool.data_dirs(),
+        }
+    }
+}
+
+/// Quotes `value` as a single shell word.
+pub fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Renders the `export PATH=...` line of `activate.sh`.
+pub fn render_sh_path(activations: &[Activation]) -> String {
+    let dirs: String = activations
+        .iter()
+        .flat_map(|activation| &activation.path_dirs)
+        .map(|dir| format!("$ENV_DIR/{}:", dir))
+        .collect();
+    format!("export PATH=\"$ENV_DIR/bin:{}$PATH\"\n", dirs)
+}
+
+/// Renders the tool-specific variables and data directories of `activate.sh`.
+pub fn render_sh_env(activations: &[Activation]) -> String {
+    let mut script = String::new();
+    for var in activations
+        .iter()
+        .flat_map(|activation| &activation.env_vars)
+    {
+        let export = format!("export {}=\"$ENV_DIR/{}\"\n", var.name, var.path);
+        if var.if_exists {
+            script.push_str(&format!(
+                "if [ -e \"$ENV_DIR/{}\" ]; then\n  {}fi\n",
+                var.path, export
+            ));
+        } else {
+            script.push_str(&export);
+        }
+    }
+
+    let data_dirs: Vec<String> = activations
+        .iter()
+        .flat_map(|activation| &activation.data_dirs)
+        .map(|dir| format!("$ENV_DIR/{}", dir))
+        .collect();
+    if !data_dirs.is_empty() {
+        let data_dirs = data_dirs.join(":");
+        script.push_str(&format!(
+            concat!(
+                "if [ -n \"$XDG_DATA_DIRS\" ]; then\n",
+                "  export XDG_DATA_DIRS=\"{0}:$XDG_DATA_DIRS\"\n",
+                "else\n",
+                "  # Provide a sensible default if the variable is not already set.\n",
+                "  export XDG_DATA_DIRS=\"{0}:/usr/local/share:/usr/share\"\n",
+                "fi\n",
+            ),
+            data_dirs
+        ));
+    }
+    script
+}
+
+/// The variables that activating the environment sets, for the in-place scripts to save.
+pub fn activated_vars(activations: &[Activation]) -> Vec<String> {
+    let mut vars: Vec<String> = ["PATH", "XDG_CONFIG_HOME", "XDG_DATA_HOME"]
+        .map(String::from)
+        .into();
+    for var in activations
+        .iter()
+        .flat_map(|activation| &activation.env_vars)
+    {
+        if !vars.contains(&var.name) {
+            vars.push(var.name.clone());
+        }
+    }
+    if activations
+        .iter()
+        .any(|activation| !activation.data_dirs.is_empty())
+    {
+        vars.push("XDG_DATA_DIRS".to_string());
+    }
+    vars
+}
+
+/// The variables that activating the environment in `env_dir` sets, computed from the
+/// current process environment the same way `activate.sh` computes them.
+pub fn activated_env(env_dir: &Path, activations: &[Activation]) -> Vec<(String, OsString)> {
+    let mut path_dirs = vec![env_dir.join("bin")];
+    path_dirs.extend(
+        activations
+            .iter()
+            .flat_map(|activation| &activation.path_dirs)
+            .map(|dir| env_dir.join(dir)),
+    );
+    let mut vars = vec![
+        ("PATH".to_string(), prepend_paths(&path_dirs, "PATH", None)),
+        (
+            "XDG_CONFIG_HOME".to_string(),
+            env_dir.join("config").into_os_string(),
+        ),
+        (
+            "XDG_DATA_HOME".to_string(),
+            env_dir.join("data").into_os_string(),
+        ),
+    ];
+
+    for var in activations
+        .iter()
+        .flat_map(|activation| &activation.env_vars)
+    {
+        let path = env_dir.join(&var.path);
+        if !var.if_exists || path.exists() {
+            vars.push((var.name.clone(), path.into_os_string()));
+        }
+    }
+
+    let data_dirs: Vec<_> = activations
+        .iter()
+        .flat_map(|activation| &activation.data_dirs)
+        .map(|dir| env_dir.join(dir))
+        .collect();
+    if !data_dirs.is_empty() {
+        vars.push((
+            "XDG_DATA_DIRS".to_string(),
+            prepend_paths(
+                &data_dirs,
+                "XDG_DATA_DIRS",
+                Some("/usr/local/share:/usr/share"),
+            ),
+        ));
+    }
+    vars
+}
+
+/// Joins `dirs` in front of the current value of the variable `name`, or of `default`
+/// if it is unset or empty.
+fn prepend_paths(dirs: &[PathBuf], name: &str, default: Option<&str>) -> OsString {
+    let mut parts: Vec<OsString> = dirs
+        .iter()
+        .map(|dir| dir.clone().into_os_string())
+        .collect();
+    match env::var_os(name).filter(|value| !value.is_empty()) {
+        Some(current) => parts.push(current),
+        None => parts.extend(default.map(OsString::from)),
+    }
+    parts.join(OsStr::new(":"))
+}
+
+/// Renders the `set -gx PATH ...` line of `activate.fish`.
+pub fn render_fish_path(activations: &[Activation]) -> String {
+    let dirs: String = activations
+        .iter()
+        .flat_map(|activation| &activation.path_dirs)
+        .map(|dir| format!("\"$ENV_DIR/{}\" ", dir))
+        .collect();
+    format!("set -gx PATH \"$ENV_DIR/bin\" {}$PATH\n", dirs)
+}
+
+/// Renders the tool-specific variables and data directories of `activate.fish`.
+pub fn render_fish_env(activations: &[Activation]) -> String {
+    let mut script = String::new();
+    for var in activations
+        .iter()
+        .flat_map(|activation| &activation.env_vars)
+    {
+        let export = format!("set -gx {} \"$ENV_DIR/{}\"\n", var.name, var.path);
+        if var.if_exists {
+            script.push_str(&format!(
+                "if test -e \"$ENV_DIR/{}\"\n    {}end\n",
+                var.path, export
+            ));
+        } else {
+            script.push_str(&export);
+        }
+    }
+
+    let data_dirs: Vec<String> = activations
+        .iter()
+        .flat_map(|activation| &activation.data_dirs)
+        .map(|dir| format!("$ENV_DIR/{}", dir))
+        .collect();
+    if !data_dirs.is_empty() {
+        let data_dirs = data_dirs.join(":");
+        script.push_str(&format!(
+            concat!(
+                "if set -q XDG_DATA_DIRS; and test -n \"$XDG_DATA_DIRS\"\n",
+                "    set -gx XDG_DATA_DIRS \"{0}:$XDG_DATA_DIRS\"\n",
+                "else\n",
+                "    set -gx XDG_DATA_DIRS \"{0}:/usr/local/share:/usr/share\"\n",
+                "end\n",
+            ),
+            data_dirs
+        ));
+    }
+    script
+}
@@ -1,14 +1,173 @@
-use clap::Parser;
+use crate::{
+    provision::policy::{self, SourcePolicy},
+    run::EnvFormat,
+    shell::Shell,
+};
+use clap::{Parser, Subcommand, ValueEnum};
 
 /// A tool to create isolated, non-destructive shell environments.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// The directory where the environment will be created.
     #[arg(default_value = "~/.isoterm")]
     pub dest_dir: String,
 
+    /// Only provision these tools (comma-separated). The environment's shell is always required.
+    #[arg(long, value_delimiter = ',', value_name = "TOOLS")]
+    pub only: Vec<String>,
+
+    /// Do not provision these tools (comma-separated).
+    #[arg(long, value_delimiter = ',', value_name = "TOOLS")]
+    pub skip: Vec<String>,
+
+    /// Provision these tools in addition to the selection (comma-separated).
+    #[arg(long, value_delimiter = ',', value_name = "TOOLS")]
+    pub with: Vec<String>,
+
+    /// The interactive shell the environment starts. Defaults to the shell the environment
+    /// already uses, or fish for a new one.
+    #[arg(long, value_enum, global = true)]
+    pub shell: Option<Shell>,
+
+    /// Keep provisioning the other tools when one fails, and keep the partial environment.
+    /// Re-running retries the tools that failed.
+    #[arg(long, global = true)]
+    pub keep_going: bool,
+
+    /// A TOML file declaring additional tools to provision from their GitHub releases.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub tools_file: Option<String>,
+
+    /// Where tools come from: the system PATH, a release download, or either.
+    #[arg(long, value_enum, default_value_t = SourcePolicy::PreferSystem, global = true)]
+    pub source: SourcePolicy,
+
+    /// Override the source policy for one tool, e.g. `--tool-source fish=prefer-download`.
+    #[arg(long, value_name = "TOOL=POLICY", value_parser = parse_tool_source, global = true)]
+    pub tool_source: Vec<(String, SourcePolicy)>,
+
+    /// Require at least this version of a system binary, e.g. `--min-version helix=25.01`.
+    /// Older system binaries are ignored in favor of a download.
+    #[arg(long, value_name = "TOOL=VERSION", value_parser = parse_min_version, global = true)]
+    pub min_version: Vec<(String, String)>,
+
+    /// Copy system binaries, and the runtime files they need, into the environment instead of
+    /// symlinking them, so the environment survives package upgrades and can be moved.
+    #[arg(long, global = true)]
+    pub vendor_system: bool,
+
+    /// Put generated shims in `bin/` that set each tool's environment and run the real binary
+    /// from `libexec/`, so the tools also work outside an activated shell. Kept on later runs.
+    #[arg(long, global = true)]
+    pub shims: bool,
+
+    /// How many seconds to wait for another isoterm run that is changing the same environment.
+    #[arg(long, value_name = "SECS", default_value_t = 30, global = true)]
+    pub lock_timeout: u64,
+
     /// Enable verbose logging. Use -v for info, -vv for debug.
-    #[arg(short, long, action = clap::ArgAction::Count)]
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
 }
+
+/// Splits a `TOOL=VALUE` argument.
+fn split_tool_arg(arg: &str) -> Result<(String, &str), String> {
+    let (tool, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected TOOL=VALUE, got '{}'", arg))?;
+    Ok((tool.to_string(), value))
+}
+
+fn parse_tool_source(arg: &str) -> Result<(String, SourcePolicy), String> {
+    let (tool, policy) = split_tool_arg(arg)?;
+    Ok((tool, SourcePolicy::from_str(policy, false)?))
+}
+
+fn parse_min_version(arg: &str) -> Result<(String, String), String> {
+    let (tool, version) = split_tool_arg(arg)?;
+    // A version that cannot be compared would make every system binary look too old.
+    if policy::parse_version(version).is_none() {
+        return Err(format!(
+            "expected a dotted version such as 3.6 or 25.01, got '{}'",
+            version
+        ));
+    }
+    Ok((tool, version.to_string()))
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Inspect an environment's binaries and report why any of them may not run.
+    Doctor {
+        /// The environment directory to inspect.
+        #[arg(default_value = "~/.isoterm")]
+        env_dir: String,
+    },
+    /// Make tools from an environment available in every shell, through shims in a host bin dir.
+    Expose {
+        /// The tools to expose.
+        #[arg(required = true)]
+        tools: Vec<String>,
+        /// The environment the tools come from.
+        #[arg(long, default_value = "~/.isoterm")]
+        env_dir: String,
+        /// The host directory to put the shims in. It should be on your PATH.
+        #[arg(long, default_value = "~/.local/bin")]
+        bin_dir: String,
+    },
+    /// Remove the shims that `expose` put on the host.
+    Unexpose {
+        /// The tools to stop exposing.
+        #[arg(required = true)]
+        tools: Vec<String>,
+        /// The environment the tools come from.
+        #[arg(long, default_value = "~/.isoterm")]
+        env_dir: String,
+    },
+    /// Delete an environment, along with the shims it exposed on the host.
+    Remove {
+        /// The environment directory to delete.
+        #[arg(default_value = "~/.isoterm")]
+        env_dir: String,
+    },
+    /// Run a command inside an environment, with the variables `activate.sh` sets, e.g.
+    /// `isoterm run ~/.isoterm -- hx --health`. Exits with the command's status.
+    Run {
+        /// The environment to run the command in.
+        #[arg(default_value = "~/.isoterm")]
+        env_dir: String,
+        /// The command and its arguments, after `--`.
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// Start an environment's interactive shell. Exits with the shell's status.
+    Shell {
+        /// The environment to start.
+        #[arg(default_value = "~/.isoterm")]
+        env_dir: String,
+    },
+    /// Print the variables `activate.sh` sets for an environment, e.g. for
+    /// `eval "$(isoterm env)"`, direnv, systemd units or IDE terminal profiles.
+    Env {
+        /// The environment to print the variables of.
+        #[arg(default_value = "~/.isoterm")]
+        env_dir: String,
+        /// The output format.
+        #[arg(long, value_enum, default_value_t = EnvFormat::Sh)]
+        format: EnvFormat,
+        /// Only print the variables whose value differs from the current environment.
+        #[arg(long)]
+        diff: bool,
+    },
+    /// Re-provision the tools in an environment whose binaries or runtime files are broken,
+    /// e.g. dangling symlinks, empty binaries or a missing runtime directory.
+    Repair {
+        /// The environment directory to repair.
+        #[arg(default_value = "~/.isoterm")]
+        env_dir: String,
+    },
+}
@@ -1,33 +1,42 @@
-use crate::{error::AppResult, provision::create_symlink};
-use anyhow::{anyhow, Context};
+use crate::{
+    activation::{self, Activation},
+    error::AppResult,
+    provision::create_symlink,
+    shell::Shell,
+};
+use anyhow::Context;
 use indicatif::ProgressBar;
-use std::{collections::HashSet, fs, path::Path, process::Command};
+use std::{collections::HashSet, fs, path::Path};
 
-/// Generates all necessary configuration files and the activation script.
-#[tracing::instrument(skip(pb), fields(env_dir = %env_dir.display()))]
-pub async fn generate_configs(env_dir: &Path, pb: &ProgressBar) -> AppResult<()> {
+/// Generates the activation script and the shell config of the installed tools.
+/// Tool-specific configuration is written by each tool's `configure` hook.
+#[tracing::instrument(skip_all, fields(env_dir = %env_dir.display()))]
+pub async fn generate_configs(
+    env_dir: &Path,
+    activations: &[Activation],
+    shell: Shell,
+    pb: &ProgressBar,
+) -> AppResult<()> {
     pb.set_message("Generating configuration files...");
 
     // Generate activate.sh
-    write_activate_script(env_dir)?;
-
-    // Generate fish config
-    write_fish_config(env_dir)?;
+    write_activate_script(env_dir, activations, shell)?;
 
-    // Generate starship config
-    write_starship_config(env_dir)?;
+    // Generate activate.bash, activate.zsh and activate.fish
+    write_in_place_scripts(env_dir, activations)?;
 
-    // Generate atuin config
-    write_atuin_config(env_dir)?;
-
-    // Generate helix config
-    write_helix_config(env_dir)?;
+    // Generate the shell's rc file
+    write_shell_config(env_dir, activations, shell)?;
 
     Ok(())
 }
 
 /// A helper to write a config file, creating parent directories if they don't exist.
-fn write_config_file(env_dir: &Path, relative_path: &str, content: &str) -> AppResult<()> {
+pub(crate) fn write_config_file(
+    env_dir: &Path,
+    relative_path: &str,
+    content: &str,
+) -> AppResult<()> {
     let final_path = env_dir.join(relative_path);
     if let Some(parent_dir) = final_path.parent() {
         fs::create_dir_all(parent_dir).with_context(|| {
@@ -43,10 +52,23 @@ fn write_config_file(env_dir: &Path, relative_path: &str, content: &str) -> AppR
 }
 
 /// Creates the main `activate.sh` script for the environment.
-#[tracing::instrument(fields(env_dir = %env_dir.display()))]
-fn write_activate_script(env_dir: &Path) -> AppResult<()> {
-    let script_content = include_str!("../templates/activate.sh");
-    write_config_file(env_dir, "activate.sh", script_content)?;
+#[tracing::instrument(skip_all, fields(env_dir = %env_dir.display()))]
+fn write_activate_script(
+    env_dir: &Path,
+    activations: &[Activation],
+    shell: Shell,
+) -> AppResult<()> {
+    // The fallback location for shells that cannot tell a sourced script where it is.
+    let absolute_env_dir = fs::canonicalize(env_dir)?;
+    let script_content = include_str!("../templates/activate.sh")
+        .replace(
+            "${ENV_DIR}$",
+            &activation::sh_quote(&absolute_env_dir.display().to_string()),
+        )
+        .replace("${PATH}$\n", &activation::render_sh_path(activations))
+        .replace("${TOOL_ENV}$\n", &activation::render_sh_env(activations))
+        .replace("${EXEC}$\n", &shell.render_exec());
+    write_config_file(env_dir, "activate.sh", &script_content)?;
 
     #[cfg(unix)]
     {
@@ -58,75 +80,43 @@ fn write_activate_script(env_dir: &Path) -> AppResult<()> {
     Ok(())
 }
 
-/// Creates the `config.fish` file with initialization commands.
-#[tracing::instrument(fields(env_dir = %env_dir.display()))]
-fn write_fish_config(env_dir: &Path) -> AppResult<()> {
-    let config_content = include_str!("../templates/config.fish");
-    write_config_file(env_dir, "config/fish/config.fish", config_content)
-}
-
-/// Creates a default `starship.toml` configuration using `starship preset`.
-#[tracing::instrument(fields(env_dir = %env_dir.display()))]
-fn write_starship_config(env_dir: &Path) -> AppResult<()> {
-    let config_path = env_dir.join("config").join("starship.toml");
-    let starship_bin = env_dir.join("bin").join("starship");
-
-    // START MODIFICATION: Remove symlink if it exists
-    if config_path.is_symlink() {
-        tracing::debug!(path = %config_path.display(), "Removing existing symlink for managed config file");
-        fs::remove_file(&config_path)?;
-    }
-    // END MODIFICATION
-
-    tracing::trace!(path = %config_path.display(), "Generating starship config");
-
-    let status = Command::new(&starship_bin)
-        .arg("preset")
-        .arg("no-empty-icons")
-        .arg("-o")
-        .arg(&config_path)
-        .status()
-        .context("Failed to execute starship preset command")?;
-
-    if !status.success() {
-        return Err(anyhow!(
-            "starship preset command failed with status: {}",
-            status
-        ));
+/// Creates the scripts that activate the environment in the current bash, zsh or fish
+/// session when sourced, instead of starting the environment's shell, and define `deactivate`.
+#[tracing::instrument(skip_all, fields(env_dir = %env_dir.display()))]
+fn write_in_place_scripts(env_dir: &Path, activations: &[Activation]) -> AppResult<()> {
+    let absolute_env_dir = activation::sh_quote(&fs::canonicalize(env_dir)?.display().to_string());
+    let vars = activation::activated_vars(activations);
+    let lines = |command: &str, indent: &str| -> String {
+        vars.iter()
+            .map(|var| format!("{}{} {}\n", indent, command, var))
+            .collect()
+    };
+
+    for shell in ["bash", "zsh"] {
+        let script_content = include_str!("../templates/activate.inplace.sh")
+            .replace("${SHELL}$", shell)
+            .replace("${ENV_DIR}$", &absolute_env_dir)
+            .replace("${SAVE}$\n", &lines("_isoterm_save", ""))
+            .replace("${RESTORE}$\n", &lines("_isoterm_restore", "  "))
+            .replace("${PATH}$\n", &activation::render_sh_path(activations))
+            .replace("${TOOL_ENV}$\n", &activation::render_sh_env(activations));
+        write_config_file(env_dir, &format!("activate.{}", shell), &script_content)?;
     }
 
-    Ok(())
-}
-
-/// Creates a default `atuin/config.toml` configuration.
-#[tracing::instrument(fields(env_dir = %env_dir.display()))]
-fn write_atuin_config(env_dir: &Path) -> AppResult<()> {
-    // Ensure the data directory exists first.
-    let atuin_data_dir = env_dir.join("data").join("atuin");
-    fs::create_dir_all(&atuin_data_dir).context("Failed to create atuin data directory")?;
-
-    let db_path = atuin_data_dir.join("history.db");
-    let db_path_str = db_path
-        .to_str()
-        .ok_or_else(|| anyhow!("Invalid non-UTF8 path for atuin database"))?;
-
-    let template_content = include_str!("../templates/atuin/config.toml.template");
-    let config_content = template_content.replace("${DB_PATH}$", &db_path_str.replace('\\', "/"));
-
-    write_config_file(env_dir, "config/atuin/config.toml", &config_content)
+    let script_content = include_str!("../templates/activate.fish")
+        .replace("${ENV_DIR}$", &absolute_env_dir)
+        .replace("${SAVE}$\n", &lines("_isoterm_save", ""))
+        .replace("${RESTORE}$\n", &lines("_isoterm_restore", "    "))
+        .replace("${PATH}$\n", &activation::render_fish_path(activations))
+        .replace("${TOOL_ENV}$\n", &activation::render_fish_env(activations));
+    write_config_file(env_dir, "activate.fish", &script_content)
 }
 
-#[tracing::instrument(fields(env_dir = %env_dir.display()))]
-fn write_helix_config(env_dir: &Path) -> AppResult<()> {
-    let config_toml_content = include_str!("../templates/helix/config.toml");
-    write_config_file(env_dir, "config/helix/config.toml", config_toml_content)?;
-
-    let languages_toml_content = include_str!("../templates/helix/languages.toml");
-    write_config_file(
-        env_dir,
-        "config/helix/languages.toml",
-        languages_toml_content,
-    )
+/// Creates the shell's rc file, e.g. `config.fish`, with the initialization commands of the
+/// installed tools.
+#[tracing::instrument(skip_all, fields(env_dir = %env_dir.display(), %shell))]
+fn write_shell_config(env_dir: &Path, activations: &[Activation], shell: Shell) -> AppResult<()> {
+    write_config_file(env_dir, shell.rc_path(), &shell.render_rc(activations))
 }
 
 /// Symlinks all directories from the user's global ~/.config into the
@@ -142,14 +132,21 @@ pub fn symlink_unmanaged_configs(env_dir: &Path) -> AppResult<()> {
         return Ok(());
     }
 
-    let managed_configs: HashSet<&str> =
-        ["fish", "starship", "atuin", "helix", "starship.toml"]
-            .iter()
-            .cloned()
-            .collect();
+    let managed_configs: HashSet<&str> = [
+        "fish",
+        "zsh",
+        "nushell",
+        "starship",
+        "atuin",
+        "helix",
+        "starship.toml",
+    ]
+    .iter()
+    .cloned()
+    .collect();
 
     tracing::debug!("Symlinking unmanaged configs");
-    for entry in fs::read_dir(&global_config_dir)? {
+    for entry in fs::read_dir(global_config_dir)? {
         let entry = entry?;
         let file_name = entry.file_name();
         let file_name_str = file_name.to_string_lossy();
@@ -157,6 +154,11 @@ pub fn symlink_unmanaged_configs(env_dir: &Path) -> AppResult<()> {
         if !managed_configs.contains(file_name_str.as_ref()) {
             let source_path = entry.path();
             let dest_path = env_config_dir.join(file_name_str.as_ref());
+            if dest_path.symlink_metadata().is_ok() {
+                // Left by an earlier run of an environment that is being completed.
+                tracing::trace!(config = %file_name_str, "Config already present in the overlay");
+                continue;
+            }
             create_symlink(&source_path, &dest_path).with_context(|| {
                 format!(
                     "Failed to symlink unmanaged config from {} to {}",
@@ -0,0 +1,106 @@
+use crate::{
+    error::AppResult,
+    provision::{elf::ElfInfo, platform},
+    shim,
+};
+use anyhow::anyhow;
+use console::style;
+use std::{env, fs, path::Path};
+
+/// Prints a report on the host and on every binary in the environment's `bin/`.
+/// Returns an error if any binary is unusable.
+#[tracing::instrument(fields(env_dir = %env_dir.display()))]
+pub fn run(env_dir: &Path) -> AppResult<()> {
+    let bin_dir = env_dir.join("bin");
+    if !bin_dir.is_dir() {
+        return Err(anyhow!(
+            "{} is not an isoterm environment",
+            env_dir.display()
+        ));
+    }
+
+    println!(
+        "{} {} {} ({})",
+        style("Host:").bold(),
+        env::consts::OS,
+        env::consts::ARCH,
+        platform::host_libc()
+    );
+
+    let mut entries: Vec<_> = fs::read_dir(&bin_dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut problems = 0;
+    for entry in entries {
+        let mut path = entry.path();
+        println!(
+            "\n{}",
+            style(entry.file_name().to_string_lossy()).bold().cyan()
+        );
+        if shim::is_shim(&path) {
+            path = env_dir.join(shim::LIBEXEC_DIR).join(entry.file_name());
+            println!("  shim for:    {}", path.display());
+        }
+        if !report_binary(&path) {
+            problems += 1;
+        }
+    }
+
+    if problems > 0 {
+        return Err(anyhow!(
+            "Found problems with {} of the environment's binaries",
+            problems
+        ));
+    }
+    println!("\n{} All binaries look usable", style("✓").green());
+    Ok(())
+}
+
+/// Prints the details of a single binary, returning whether it looks usable.
+fn report_binary(path: &Path) -> bool {
+    if path.is_symlink() {
+        match fs::canonicalize(path) {
+            Ok(target) => println!("  target:      {}", target.display()),
+            Err(_) => {
+                println!("  {} dangling symlink", style("✗").red());
+                return false;
+            }
+        }
+    }
+
+    let info = match ElfInfo::read(path) {
+        Ok(Some(info)) => info,
+        Ok(None) => {
+            println!("  format:      not an ELF binary");
+            return true;
+        }
+        Err(e) => {
+            println!("  {} {:#}", style("✗").red(), e);
+            return false;
+        }
+    };
+
+    println!(
+        "  format:      ELF {}-bit {}",
+        if info.is_64 { 64 } else { 32 },
+        info.machine_name()
+    );
+    println!(
+        "  interpreter: {}",
+        info.interpreter.as_deref().unwrap_or("none (static)")
+    );
+    if let Some((major, minor)) = info.required_glibc {
+        println!("  needs glibc: {}.{}", major, minor);
+    }
+    if !info.needed.is_empty() {
+        println!("  libraries:   {}", info.needed.join(", "));
+    }
+
+    match info.check_runnable() {
+        Ok(()) => true,
+        Err(reason) => {
+            println!("  {} {}", style("✗").red(), reason);
+            false
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    error::AppResult,
+    lock,
+    provision::Tool,
+    shim,
+    state::{self, EnvState},
+};
+use anyhow::{Context, anyhow};
+use console::style;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Puts a shim for each of `tools` into the host's `bin_dir`, so they run without activating
+/// the environment. Only files that isoterm created for this environment are overwritten.
+#[tracing::instrument(skip(tools), fields(env_dir = %env_dir.display(), bin_dir = %bin_dir.display()))]
+pub async fn expose(
+    env_dir: &Path,
+    bin_dir: &Path,
+    tools: &[Arc<dyn Tool>],
+    lock_timeout: Duration,
+) -> AppResult<()> {
+    state::check_environment(env_dir)?;
+    let _lock = lock::acquire(env_dir, lock_timeout).await?;
+    let mut state = EnvState::load(env_dir)?;
+
+    for tool in tools {
+        let binary = env_dir.join("bin").join(tool.binary_name());
+        if binary.symlink_metadata().is_err() {
+            return Err(anyhow!(
+                "{} is not installed in {}",
+                tool.name(),
+                env_dir.display()
+            ));
+        }
+
+        let host_path = bin_dir.join(tool.binary_name());
+        let ours = state.exposed.get(tool.name()) == Some(&host_path) && shim::is_shim(&host_path);
+        if host_path.symlink_metadata().is_ok() && !ours {
+            return Err(anyhow!(
+                "Refusing to overwrite {}, which isoterm did not create for this environment",
+                host_path.display()
+            ));
+        }
+
+        // Exposed before under another bin dir: move the shim rather than leave a stale one.
+        if let Some(previous) = state.exposed.get(tool.name())
+            && *previous != host_path
+        {
+            remove_shim(previous)?;
+        }
+
+        fs::create_dir_all(bin_dir)
+            .with_context(|| format!("Failed to create {}", bin_dir.display()))?;
+        let target = format!("bin/{}", tool.binary_name());
+        shim::write(&host_path, env_dir, tool.as_ref(), &target)?;
+        state
+            .exposed
+            .insert(tool.name().to_string(), host_path.clone());
+        state.save(env_dir)?;
+        println!(
+            "{} Exposed {} as {}",
+            style("✓").green(),
+            style(tool.name()).bold(),
+            style(host_path.display()).cyan()
+        );
+    }
+
+    let on_path =
+        env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| dir == bin_dir));
+    if !on_path {
+        println!(
+            "{} {} is not on your PATH; add it to run the exposed tools by name.",
+            style("›").yellow(),
+            bin_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Removes the host shims of the tools named in `names`.
+#[tracing::instrument(fields(env_dir = %env_dir.display()))]
+pub async fn unexpose(env_dir: &Path, names: &[String], lock_timeout: Duration) -> AppResult<()> {
+    state::check_environment(env_dir)?;
+    let _lock = lock::acquire(env_dir, lock_timeout).await?;
+    let mut state = EnvState::load(env_dir)?;
+
+    for name in names {
+        let host_path = state
+            .exposed
+            .remove(name)
+            .ok_or_else(|| anyhow!("{} is not exposed from {}", name, env_dir.display()))?;
+        remove_shim(&host_path)?;
+        state.save(env_dir)?;
+        println!(
+            "{} Removed {}",
+            style("✓").green(),
+            style(host_path.display()).cyan()
+        );
+    }
+    Ok(())
+}
+
+/// Deletes the environment in `env_dir` and the shims it exposed on the host.
+#[tracing::instrument(fields(env_dir = %env_dir.display()))]
+pub async fn remove_environment(env_dir: &Path, lock_timeout: Duration) -> AppResult<()> {
+    state::check_environment(env_dir)?;
+    let lock = lock::acquire(env_dir, lock_timeout).await?;
+    let state = EnvState::load(env_dir)?;
+
+    for host_path in state.exposed.values() {
+        remove_shim(host_path)?;
+    }
+    fs::remove_dir_all(env_dir)
+        .with_context(|| format!("Failed to remove {}", env_dir.display()))?;
+    lock.remove()?;
+    println!(
+        "{} Removed the environment in {}",
+        style("✓").green(),
+        style(env_dir.display()).cyan()
+    );
+    Ok(())
+}
+
+/// Removes an exposed shim, unless it has since been replaced by something isoterm did not write.
+fn remove_shim(path: &Path) -> AppResult<()> {
+    if path.symlink_metadata().is_err() {
+        return Ok(());
+    }
+    if !shim::is_shim(path) {
+        tracing::warn!(path = %path.display(), "Leaving replaced shim in place");
+        println!(
+            "{} Left {} in place: it is no longer a shim created by isoterm",
+            style("›").yellow(),
+            path.display()
+        );
+        return Ok(());
+    }
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+}
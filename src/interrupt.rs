@@ -0,0 +1,121 @@
+use crate::error::AppResult;
+use console::style;
+use std::fmt;
+use std::sync::{LazyLock, OnceLock};
+use tokio_util::sync::CancellationToken;
+
+/// Cancelled when the first SIGINT or SIGTERM arrives.
+static CANCEL: LazyLock<CancellationToken> = LazyLock::new(CancellationToken::new);
+static RECEIVED: OnceLock<Signal> = OnceLock::new();
+
+/// A signal that asks isoterm to stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Interrupt,
+    Terminate,
+}
+
+impl Signal {
+    /// The conventional exit code of a process stopped by this signal (128 + signal number).
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Signal::Interrupt => 130,
+            Signal::Terminate => 143,
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::Interrupt => write!(f, "SIGINT"),
+            Signal::Terminate => write!(f, "SIGTERM"),
+        }
+    }
+}
+
+/// The error a run ends with after it was stopped by a signal.
+#[derive(Debug)]
+pub struct Interrupted(pub Signal);
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Interrupted by {}", self.0)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// The token that provisioning work watches to stop early.
+pub fn token() -> &'static CancellationToken {
+    &CANCEL
+}
+
+/// Fails with [`Interrupted`] once a stop signal has arrived, so that callers bail out
+/// instead of retrying or falling back to another source.
+pub fn check() -> AppResult<()> {
+    match RECEIVED.get() {
+        Some(signal) => Err(Interrupted(*signal).into()),
+        None => Ok(()),
+    }
+}
+
+/// Listens for SIGINT and SIGTERM in the background. The first signal cancels
+/// [`token`], so in-flight work can wind down and roll back; a second one exits at once.
+pub fn install() -> AppResult<()> {
+    let mut signals = Signals::new()?;
+    tokio::spawn(async move {
+        let signal = signals.recv().await;
+        tracing::info!(%signal, "Received stop signal, cancelling");
+        let _ = RECEIVED.set(signal);
+        CANCEL.cancel();
+
+        let signal = signals.recv().await;
+        eprintln!(
+            "\n{} Received {} again, exiting now",
+            style("✗").red(),
+            signal
+        );
+        std::process::exit(signal.exit_code());
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> AppResult<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    async fn recv(&mut self) -> Signal {
+        tokio::select! {
+            _ = self.interrupt.recv() => Signal::Interrupt,
+            _ = self.terminate.recv() => Signal::Terminate,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> AppResult<Self> {
+        Ok(Self)
+    }
+
+    async fn recv(&mut self) -> Signal {
+        let _ = tokio::signal::ctrl_c().await;
+        Signal::Interrupt
+    }
+}
@@ -0,0 +1,142 @@
+use crate::error::AppResult;
+use anyhow::{Context, anyhow};
+use console::style;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{self, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often a waiting run checks whether the lock was released.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// An exclusive advisory lock on an environment directory, held while it is being changed.
+/// The lock is released when this is dropped, or by the OS if the process dies.
+#[derive(Debug)]
+pub struct EnvLock {
+    // Kept open for the lifetime of the lock.
+    _file: File,
+    path: PathBuf,
+}
+
+impl EnvLock {
+    /// Deletes the lock file, for a run that removed the environment, then releases the lock.
+    /// A run still waiting on the deleted file notices and locks a new one.
+    pub fn remove(self) -> AppResult<()> {
+        fs::remove_file(&self.path)
+            .with_context(|| format!("Failed to remove {}", self.path.display()))
+    }
+}
+
+impl Drop for EnvLock {
+    fn drop(&mut self) {
+        tracing::debug!(path = %self.path.display(), "Released environment lock");
+    }
+}
+
+/// The lock file of `env_dir`, which sits next to the environment (`<env_dir>.lock`) so
+/// that cleaning up or removing the environment does not delete a lock that is still held.
+fn lock_path(env_dir: &Path) -> AppResult<PathBuf> {
+    let env_dir = path::absolute(env_dir)
+        .with_context(|| format!("Failed to resolve {}", env_dir.display()))?;
+    let name = env_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("{} cannot be used as an environment", env_dir.display()))?;
+    let mut lock_name = OsString::from(name);
+    lock_name.push(".lock");
+    Ok(env_dir.with_file_name(lock_name))
+}
+
+/// Takes the lock on `env_dir`, creating its parent directory if needed. If another run
+/// holds it, waits up to `timeout` for it to be released, reporting the PID of the holder.
+#[tracing::instrument(fields(env_dir = %env_dir.display()))]
+pub async fn acquire(env_dir: &Path, timeout: Duration) -> AppResult<EnvLock> {
+    let path = lock_path(env_dir)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let started = Instant::now();
+    let mut reported = false;
+    let mut file = open(&path)?;
+    loop {
+        match file.try_lock() {
+            // `remove` deletes the lock file, so the one this run locked may no longer be the
+            // environment's lock. Try again with the file that is there now.
+            Ok(()) if !is_current(&file, &path) => {
+                tracing::debug!("Lock file was removed while waiting for it");
+                file = open(&path)?;
+            }
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) => {
+                let holder = read_pid(&mut file)
+                    .map(|pid| format!(" (PID {})", pid))
+                    .unwrap_or_default();
+                if started.elapsed() >= timeout {
+                    return Err(anyhow!(
+                        "Another isoterm run{} is using {}; gave up waiting after {}s",
+                        holder,
+                        env_dir.display(),
+                        timeout.as_secs()
+                    ));
+                }
+                if !reported {
+                    eprintln!(
+                        "{} Waiting for another isoterm run{} to finish with {}...",
+                        style("…").yellow(),
+                        holder,
+                        env_dir.display()
+                    );
+                    reported = true;
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+    }
+
+    // The PID of a crashed run may still be in the file, but the OS has dropped its lock.
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{}", std::process::id())?;
+    file.flush()?;
+    tracing::debug!(path = %path.display(), "Acquired environment lock");
+
+    Ok(EnvLock { _file: file, path })
+}
+
+fn open(path: &Path) -> AppResult<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {}", path.display()))
+}
+
+/// Whether `file` is still the one at `path`, i.e. it was not removed after being opened.
+#[cfg(unix)]
+fn is_current(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(opened), Ok(current)) => opened.dev() == current.dev() && opened.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+/// Windows does not delete files that are open, so the file is always current.
+#[cfg(not(unix))]
+fn is_current(_file: &File, _path: &Path) -> bool {
+    true
+}
+
+/// Reads the PID recorded in the lock file, if any.
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
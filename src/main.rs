@@ -1,31 +1,54 @@
+mod activation;
 mod cli;
 mod config;
+mod doctor;
 mod error;
+mod expose;
+mod interrupt;
+mod lock;
 mod provision;
+mod repair;
+mod run;
+mod shell;
+mod shim;
+mod state;
 
 use crate::{
-    cli::Cli,
+    activation::Activation,
+    cli::{Cli, Command},
     error::AppResult,
+    interrupt::Interrupted,
+    lock::EnvLock,
     provision::{
-        ProvisionContext, atuin::Atuin, fish::Fish, helix::Helix, provision_tool, ripgrep::Ripgrep,
-        starship::Starship, zoxide::Zoxide,
+        ConfigureFailed, ProvisionContext, Tool,
+        declared::load_declared_tools,
+        graph::{ProvisionGraph, StepOutcome},
+        policy::SourcePolicies,
+        provision_tool,
+        registry::ToolRegistry,
     },
+    shell::Shell,
+    state::EnvState,
 };
-use anyhow::Context;
+use anyhow::{Context, anyhow};
 use clap::Parser;
 use console::style;
-use futures::future::try_join_all;
 use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
     if let Err(e) = run().await {
         // Using eprintln to ensure the error message is visible even if the UI is active.
-        eprintln!("\n{} {}", style("Error:").red().bold(), e);
-        std::process::exit(1);
+        eprintln!("\n{} {:#}", style("Error:").red().bold(), e);
+        let code = match e.downcast_ref::<Interrupted>() {
+            Some(Interrupted(signal)) => signal.exit_code(),
+            None => 1,
+        };
+        std::process::exit(code);
     }
 }
 
@@ -47,11 +70,189 @@ async fn run() -> AppResult<()> {
             .init();
     }
 
-    let env_dir = PathBuf::from(shellexpand::tilde(&cli.dest_dir).to_string());
+    match &cli.command {
+        Some(Command::Doctor { env_dir }) => doctor::run(&expand_path(env_dir)),
+        Some(Command::Expose {
+            tools,
+            env_dir,
+            bin_dir,
+        }) => {
+            let registry = tool_registry(&cli)?;
+            // `tools` is never empty, so this selects exactly the named tools.
+            let tools = registry.select(tools, &[], &[])?;
+            let bin_dir = std::path::absolute(expand_path(bin_dir))?;
+            expose::expose(
+                &expand_path(env_dir),
+                &bin_dir,
+                &tools,
+                Duration::from_secs(cli.lock_timeout),
+            )
+            .await
+        }
+        Some(Command::Unexpose { tools, env_dir }) => {
+            expose::unexpose(
+                &expand_path(env_dir),
+                tools,
+                Duration::from_secs(cli.lock_timeout),
+            )
+            .await
+        }
+        Some(Command::Remove { env_dir }) => {
+            expose::remove_environment(&expand_path(env_dir), Duration::from_secs(cli.lock_timeout))
+                .await
+        }
+        Some(Command::Run { env_dir, command }) => run::run(&expand_path(env_dir), command),
+        Some(Command::Shell { env_dir }) => run::shell(&expand_path(env_dir)),
+        Some(Command::Env {
+            env_dir,
+            format,
+            diff,
+        }) => run::print_env(&expand_path(env_dir), *format, *diff),
+        Some(Command::Repair { env_dir }) => repair_environment(&cli, &expand_path(env_dir)).await,
+        None => {
+            let env_dir = expand_path(&cli.dest_dir);
+            // Taken before anything else, so a run that gives up waiting never cleans up.
+            let lock = lock::acquire(&env_dir, Duration::from_secs(cli.lock_timeout)).await?;
+            let registry = tool_registry(&cli)?;
+            // An existing environment keeps its shell unless another one is asked for.
+            let shell = match cli.shell {
+                Some(shell) => shell,
+                None => EnvState::load(&env_dir)?.shell,
+            };
+            let graph = ProvisionGraph::new(select_tools(&cli, &registry, shell)?, shell)?;
+            let policies = source_policies(&cli, &registry)?;
+            let options = CreateOptions {
+                keep_going: cli.keep_going,
+                shims: cli.shims,
+                shell,
+            };
+            create_environment(&env_dir, lock, graph, policies, options).await
+        }
+    }
+}
+
+/// Re-provisions the broken tools in an existing environment, leaving the rest alone.
+async fn repair_environment(cli: &Cli, env_dir: &Path) -> AppResult<()> {
+    state::check_environment(env_dir)?;
+    // Diagnosed under the lock, so a run still provisioning a tool does not look broken.
+    let lock = lock::acquire(env_dir, Duration::from_secs(cli.lock_timeout)).await?;
+    let registry = tool_registry(cli)?;
+    let installed = repair::installed_tools(&registry, env_dir);
+    if !installed
+        .iter()
+        .any(|tool| repair::diagnose(tool.as_ref(), env_dir).is_some())
+    {
+        println!(
+            "{} Nothing to repair in {}",
+            style("✓").green(),
+            style(env_dir.display()).cyan()
+        );
+        return Ok(());
+    }
+
+    // Going over every installed tool, not just the broken ones, regenerates the
+    // configuration files with all of them; the intact ones are left as they are.
+    let shell = EnvState::load(env_dir)?.shell;
+    let graph = ProvisionGraph::new(installed, shell)?;
+    let options = CreateOptions {
+        keep_going: cli.keep_going,
+        shims: cli.shims,
+        shell,
+    };
+    create_environment(
+        env_dir,
+        lock,
+        graph,
+        source_policies(cli, &registry)?,
+        options,
+    )
+    .await
+}
+
+/// Builds the registry of built-in tools and the ones declared in `--tools-file`.
+fn tool_registry(cli: &Cli) -> AppResult<ToolRegistry> {
+    let mut registry = ToolRegistry::with_builtins();
+    if let Some(path) = &cli.tools_file {
+        registry.register_declared(load_declared_tools(&expand_path(path))?)?;
+    }
+    Ok(registry)
+}
+
+/// Resolves `--only`, `--skip` and `--with` into the tools to provision, in provisioning order.
+/// The shells other than the environment's own are only provisioned when named.
+fn select_tools(cli: &Cli, registry: &ToolRegistry, shell: Shell) -> AppResult<Vec<Arc<dyn Tool>>> {
+    let named = |name: &str| cli.only.iter().chain(&cli.with).any(|n| n == name);
+    let other_shells: Vec<&str> = Shell::ALL
+        .iter()
+        .filter(|other| **other != shell)
+        .map(|other| other.tool_name())
+        .collect();
+    let selected: Vec<Arc<dyn Tool>> = registry
+        .select(&cli.only, &cli.skip, &cli.with)?
+        .into_iter()
+        .filter(|tool| !other_shells.contains(&tool.name()) || named(tool.name()))
+        .collect();
+
+    // The activation script execs the shell, so it cannot be left out.
+    if !selected.iter().any(|tool| tool.name() == shell.tool_name()) {
+        return Err(anyhow!(
+            "{} is the environment's shell and cannot be skipped",
+            shell
+        ));
+    }
+    tracing::debug!(tools = ?selected.iter().map(|tool| tool.name()).collect::<Vec<_>>(), "Selected tools");
+    Ok(selected)
+}
+
+/// Builds the source policies for this run from the command line, rejecting unknown tools.
+fn source_policies(cli: &Cli, registry: &ToolRegistry) -> AppResult<SourcePolicies> {
+    let mut policies = SourcePolicies {
+        default: cli.source,
+        vendor_system: cli.vendor_system,
+        ..Default::default()
+    };
+    for (tool, policy) in &cli.tool_source {
+        registry.check(tool)?;
+        policies.per_tool.insert(tool.clone(), *policy);
+    }
+    for (tool, version) in &cli.min_version {
+        registry.check(tool)?;
+        policies.min_versions.insert(tool.clone(), version.clone());
+    }
+    Ok(policies)
+}
+
+/// Expands a leading `~` in a user-supplied path.
+fn expand_path(path: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(path).to_string())
+}
+
+/// How `create_environment` behaves, as set on the command line.
+struct CreateOptions {
+    keep_going: bool,
+    shims: bool,
+    shell: Shell,
+}
 
-    // The entire setup is wrapped in a closure that returns a Result.
+/// Creates (or completes) the environment in `env_dir`, which `lock` is held on.
+async fn create_environment(
+    env_dir: &Path,
+    lock: EnvLock,
+    graph: ProvisionGraph,
+    policies: SourcePolicies,
+    options: CreateOptions,
+) -> AppResult<()> {
+    // `lock` is held until the end, so no other run touches the environment while it changes.
+    interrupt::install()?;
+
+    // An existing environment is only ever repaired or completed, never removed. This is
+    // decided under the lock, so a run that waited for another one to create the environment
+    // does not take it for its own. Environments from before the marker still have `bin/`.
+    let created = !env_dir.join(state::MARKER_FILE).exists() && !env_dir.join("bin").exists();
+
+    // The entire setup is wrapped in an async block that returns a Result.
     // This allows us to handle any error gracefully by cleaning up the environment directory.
-    let setup_result = (|| async {
+    let setup_result = async {
         let client = reqwest::Client::builder()
             .user_agent("isoterm")
             .build()
@@ -85,13 +286,31 @@ async fn run() -> AppResult<()> {
         fs::create_dir_all(&data_dir)?;
         tracing::trace!(path = %data_dir.display(), "Created data directory");
 
+        // Lets other runs recognise the environment, e.g. to keep it out of system lookups.
+        state::write_marker(env_dir)?;
+
         // --- Create the configuration overlay ---
-        config::symlink_unmanaged_configs(&env_dir)?;
+        config::symlink_unmanaged_configs(env_dir)?;
         tracing::info!("Created symlink overlay for unmanaged configurations");
 
+        // --- Repair broken tools ---
+        // A dangling symlink or a half-installed tool would otherwise look provisioned,
+        // or make provisioning fail. Removing it lets the graph provision it afresh.
+        for tool in graph.tools() {
+            if let Some(problem) = repair::diagnose(tool.as_ref(), env_dir) {
+                tracing::info!(tool = tool.name(), %problem, "Repairing tool");
+                mp.println(format!(
+                    "{} Repairing {}: {}",
+                    style("›").yellow(),
+                    style(tool.name()).bold(),
+                    problem
+                ))?;
+                provision::remove_tool_files(tool.as_ref(), env_dir)?;
+            }
+        }
+
         // --- Overall Progress Bar ---
-        let tools_to_provision = ["fish", "starship", "zoxide", "atuin", "ripgrep", "helix"];
-        let total_steps = (tools_to_provision.len() + 1) as u64; // Tools + config step
+        let total_steps = (graph.tools().len() + 1) as u64; // Tools + config step
 
         let overall_pb = mp.add(ProgressBar::new(total_steps));
         let overall_style = ProgressStyle::with_template("[{pos}/{len}] {wide_msg}")?;
@@ -99,96 +318,208 @@ async fn run() -> AppResult<()> {
         overall_pb.set_message("Initializing...");
         let overall_pb = Arc::new(overall_pb);
 
-        // --- Spawn all provisioning tasks ---
+        // --- Shared provisioning context ---
         let context = ProvisionContext {
-            env_dir: env_dir.clone(),
+            env_dir: env_dir.to_path_buf(),
             client,
+            policies: Arc::new(policies),
+            shell: options.shell,
+            recorded: Arc::new(EnvState::load(env_dir)?.tools),
         };
 
-        let tasks = vec![
-            tokio::spawn(provision_tool(
-                Fish,
-                context.clone(),
-                mp.clone(),
-                overall_pb.clone(),
-            )),
-            tokio::spawn(provision_tool(
-                Starship,
-                context.clone(),
-                mp.clone(),
-                overall_pb.clone(),
-            )),
-            tokio::spawn(provision_tool(
-                Zoxide,
-                context.clone(),
-                mp.clone(),
-                overall_pb.clone(),
-            )),
-            tokio::spawn(provision_tool(
-                Atuin,
-                context.clone(),
-                mp.clone(),
-                overall_pb.clone(),
-            )),
-            tokio::spawn(provision_tool(
-                Ripgrep,
-                context.clone(),
-                mp.clone(),
-                overall_pb.clone(),
-            )),
-            tokio::spawn(provision_tool(
-                Helix,
-                context.clone(),
-                mp.clone(),
-                overall_pb.clone(),
-            )),
-        ];
-
-        // --- Await tasks concurrently ---
-        let results = try_join_all(tasks)
-            .await
-            .context("A provisioning task panicked or was cancelled")?;
-        for result in results {
-            result.context("A provisioning task returned an error")?;
+        // --- Run the provisioning graph ---
+        // Each tool starts as soon as the tools it depends on are in place.
+        let outcomes = graph
+            .run(interrupt::token(), |tool| {
+                provision_tool(tool, context.clone(), mp.clone(), overall_pb.clone())
+            })
+            .await;
+        let interrupted = interrupt::check().err();
+
+        let mut state = EnvState::load(env_dir)?;
+        state.shims |= options.shims;
+        if state.shell != options.shell {
+            // Init code recorded for the previous shell would not run in this one. The tools
+            // this run provisions are recorded afresh below; the others lose their init until
+            // a run selects them again.
+            for activation in &mut state.activations {
+                activation.shell_init = None;
+            }
+        }
+        state.shell = options.shell;
+        let mut failures = Vec::new();
+        let mut shell_failed = false;
+        for (tool, outcome) in graph.tools().iter().zip(outcomes) {
+            // Whether the tool's files stay: a tool blocked by a dependency never started,
+            // and one whose configure hook failed has its binary in place.
+            let (reason, keep_files) = match outcome {
+                StepOutcome::Done(provisioned) => {
+                    if let Some(provenance) = provisioned.provenance {
+                        state.tools.insert(tool.name().to_string(), provenance);
+                    }
+                    state.failed.remove(tool.name());
+                    if state.shims {
+                        shim::install(env_dir, tool.as_ref())?;
+                    }
+                    state.record_activation(provisioned.activation);
+                    continue;
+                }
+                // Never started, so there is nothing to record or undo.
+                StepOutcome::Cancelled => continue,
+                StepOutcome::Failed(e) => {
+                    let reason = format!("{:#}", e);
+                    match e.downcast::<ConfigureFailed>() {
+                        Ok(failed) => {
+                            if let Some(provenance) = failed.provenance {
+                                state.tools.insert(tool.name().to_string(), provenance);
+                            }
+                            state.record_activation(Activation::of(tool.as_ref(), options.shell));
+                            (reason, true)
+                        }
+                        Err(_) => (reason, false),
+                    }
+                }
+                StepOutcome::Blocked { .. } if interrupted.is_some() => continue,
+                StepOutcome::Blocked { dependency } => (
+                    format!(
+                        "not provisioned because its dependency {} failed",
+                        dependency
+                    ),
+                    true,
+                ),
+            };
+            if interrupted.is_some() {
+                // Roll back only what the stopped steps left half-installed.
+                if !keep_files {
+                    provision::remove_tool_files(tool.as_ref(), env_dir)?;
+                    state.forget_activation(tool.name());
+                }
+                continue;
+            }
+            if options.keep_going || !created {
+                if !keep_files {
+                    // Leave the tool absent, so the next run provisions it from scratch.
+                    provision::remove_tool_files(tool.as_ref(), env_dir)?;
+                    state.tools.remove(tool.name());
+                    state.forget_activation(tool.name());
+                }
+                state.failed.insert(tool.name().to_string(), reason.clone());
+            }
+            shell_failed |= tool.name() == options.shell.tool_name();
+            failures.push(format!("{}: {}", tool.name(), reason));
+        }
+        state.save(env_dir)?;
+        if let Some(e) = interrupted {
+            overall_pb.finish_and_clear();
+            mp.clear()?;
+            return Err(e);
+        }
+        // activate.sh and `isoterm shell` exec the shell, so an environment without it
+        // is unusable even with --keep-going.
+        let error = if shell_failed {
+            Some(anyhow!(
+                "{} is the environment's shell and could not be provisioned:\n  {}",
+                options.shell,
+                failures.join("\n  ")
+            ))
+        } else if !failures.is_empty() && !options.keep_going {
+            Some(anyhow!(
+                "Failed to provision {} of {} tools:\n  {}",
+                failures.len(),
+                graph.tools().len(),
+                failures.join("\n  ")
+            ))
+        } else {
+            None
+        };
+        // A new environment that failed is removed, so there is nothing to configure.
+        if created && let Some(e) = error {
+            return Err(e);
         }
 
         // --- Configuration Step ---
         overall_pb.set_message("Generating configuration files...");
-        config::generate_configs(&env_dir, &overall_pb).await?;
+        // Rendered from every tool in the environment, not just the ones this run selected,
+        // and also when a kept environment lost a tool, so nothing points at its files.
+        config::generate_configs(env_dir, &state.activations, options.shell, &overall_pb).await?;
+        state.save(env_dir)?;
         overall_pb.println(format!(
             "{} Generated configuration files",
             style("✓").green()
         ));
         overall_pb.inc(1);
+        if let Some(e) = error {
+            return Err(e);
+        }
 
         // --- Finalization ---
         overall_pb.finish_and_clear();
-        mp.println(format!(
-            "\n{} Environment setup complete!",
-            style("🚀").green()
-        ))?;
-        mp.println("To activate your new shell environment, run:".to_string())?;
+        if failures.is_empty() {
+            mp.println(format!(
+                "\n{} Environment setup complete!",
+                style("🚀").green()
+            ))?;
+        } else {
+            mp.println(format!(
+                "\n{} Environment set up without {} of {} tools",
+                style("⚠").yellow(),
+                failures.len(),
+                graph.tools().len()
+            ))?;
+        }
+        mp.println("To activate your new shell environment, run:")?;
         mp.println(format!(
             "\n  source {}\n",
             env_dir.join("activate.sh").display()
         ))?;
 
-        Ok(())
-    })()
+        Ok(failures)
+    }
     .await;
 
     // --- Transactional Cleanup ---
-    if let Err(e) = setup_result {
-        eprintln!("\n{} {}", style("Fatal:").red().bold(), style(&e).red());
-        eprintln!(
-            "{}",
-            style("Cleaning up partially created environment...").yellow()
-        );
-        fs::remove_dir_all(&env_dir)
-            .context("Failed to clean up environment directory during error recovery")?;
-        eprintln!("{}", style("Cleanup complete.").green());
-        return Err(e);
-    }
+    let failed = match setup_result {
+        Ok(failed) => failed,
+        Err(e) if e.is::<Interrupted>() => {
+            // The tools that finished are kept, so a re-run picks up where this one stopped.
+            eprintln!(
+                "\n{} rolled back the tools that were in progress; run isoterm again to finish",
+                style("Stopped:").yellow().bold()
+            );
+            return Err(e);
+        }
+        Err(e) => {
+            eprintln!(
+                "\n{} {}",
+                style("Fatal:").red().bold(),
+                style(format!("{:#}", e)).red()
+            );
+            if !created {
+                eprintln!(
+                    "{}",
+                    style("Rolled back the tools that failed; the rest of the environment is unchanged.")
+                        .yellow()
+                );
+                return Err(e);
+            }
+            eprintln!(
+                "{}",
+                style("Cleaning up partially created environment...").yellow()
+            );
+            fs::remove_dir_all(env_dir)
+                .context("Failed to clean up environment directory during error recovery")?;
+            lock.remove()?;
+            eprintln!("{}", style("Cleanup complete.").green());
+            return Err(e);
+        }
+    };
 
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "Failed to provision {} tool(s); run isoterm again to retry them:\n  {}",
+            failed.len(),
+            failed.join("\n  ")
+        ));
+    }
     Ok(())
 }
@@ -1,7 +1,16 @@
-use super::Tool;
+use super::{ProvisionContext, Tool};
+use crate::{
+    config::write_config_file,
+    error::AppResult,
+    shell::{Shell, nu_autoload},
+};
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use std::fs;
 
 pub struct Atuin;
 
+#[async_trait]
 impl Tool for Atuin {
     fn name(&self) -> &'static str {
         "atuin"
@@ -14,4 +23,41 @@ impl Tool for Atuin {
     fn binary_name(&self) -> &'static str {
         "atuin"
     }
+
+    fn shell_init(&self, shell: Shell) -> Option<String> {
+        let init = match shell {
+            Shell::Fish => "atuin init fish | source".to_string(),
+            Shell::Zsh => "eval \"$(atuin init zsh)\"".to_string(),
+            Shell::Nushell => nu_autoload("atuin", "atuin init nu"),
+        };
+        Some(init)
+    }
+
+    fn min_glibc(&self) -> Option<(u32, u32)> {
+        // Atuin's GNU binary is built against glibc 2.35.
+        Some((2, 35))
+    }
+
+    /// Creates a default `atuin/config.toml` configuration.
+    #[tracing::instrument(skip_all, fields(tool = self.name()))]
+    async fn configure(&self, context: &ProvisionContext) -> AppResult<()> {
+        // Ensure the data directory exists first.
+        let atuin_data_dir = context.env_dir.join("data").join("atuin");
+        fs::create_dir_all(&atuin_data_dir).context("Failed to create atuin data directory")?;
+
+        let db_path = atuin_data_dir.join("history.db");
+        let db_path_str = db_path
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid non-UTF8 path for atuin database"))?;
+
+        let template_content = include_str!("../../templates/atuin/config.toml.template");
+        let config_content =
+            template_content.replace("${DB_PATH}$", &db_path_str.replace('\\', "/"));
+
+        write_config_file(
+            &context.env_dir,
+            "config/atuin/config.toml",
+            &config_content,
+        )
+    }
 }
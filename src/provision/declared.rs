@@ -0,0 +1,119 @@
+use super::{Tool, graph::Dependency};
+use crate::{error::AppResult, shell::Shell};
+use anyhow::Context;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+/// A tools file, e.g.:
+///
+/// ```toml
+/// [[tools]]
+/// name = "fd"
+/// repo = "sharkdp/fd"
+/// min-version = "9.0"
+///
+/// [tools.shell-init]
+/// fish = "fd --gen-completions fish | source"
+/// zsh = "eval \"$(fd --gen-completions zsh)\""
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ToolsFile {
+    #[serde(default)]
+    tools: Vec<DeclaredTool>,
+}
+
+/// A tool declared in a tools file, provisioned from its GitHub releases like the built-in ones.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DeclaredTool {
+    name: String,
+    /// The GitHub repository, as `owner/name`.
+    repo: String,
+    /// The binary's file name. Defaults to the tool name.
+    binary: Option<String>,
+    /// The path of the binary within the release archive, if it's not at the root.
+    /// The whole archive is then kept in the environment.
+    path_in_archive: Option<String>,
+    min_version: Option<String>,
+    probe_args: Option<Vec<String>>,
+    /// Code that sets the tool up in interactive shells.
+    shell_init: Option<ShellInit>,
+    /// Tools that must be provisioned before this one.
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
+/// A tool's shell setup: fish code, or code for each shell that needs any.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ShellInit {
+    Fish(String),
+    PerShell(PerShellInit),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PerShellInit {
+    fish: Option<String>,
+    zsh: Option<String>,
+    nushell: Option<String>,
+}
+
+/// Reads the tools declared in the TOML file at `path`.
+pub fn load_declared_tools(path: &Path) -> AppResult<Vec<DeclaredTool>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let file: ToolsFile =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(file.tools)
+}
+
+impl Tool for DeclaredTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    fn binary_name(&self) -> &str {
+        self.binary.as_deref().unwrap_or(&self.name)
+    }
+
+    fn path_in_archive(&self) -> Option<&str> {
+        self.path_in_archive.as_deref()
+    }
+
+    fn min_version(&self) -> Option<&str> {
+        self.min_version.as_deref()
+    }
+
+    fn probe_args(&self) -> Vec<&str> {
+        match &self.probe_args {
+            Some(args) => args.iter().map(String::as_str).collect(),
+            None => vec!["--version"],
+        }
+    }
+
+    fn dependencies(&self, _shell: Shell) -> Vec<Dependency> {
+        self.depends_on
+            .iter()
+            .cloned()
+            .map(Dependency::Tool)
+            .collect()
+    }
+
+    fn shell_init(&self, shell: Shell) -> Option<String> {
+        match (&self.shell_init, shell) {
+            (Some(ShellInit::Fish(init)), Shell::Fish) => Some(init.clone()),
+            (Some(ShellInit::PerShell(init)), _) => match shell {
+                Shell::Fish => init.fish.clone(),
+                Shell::Zsh => init.zsh.clone(),
+                Shell::Nushell => init.nushell.clone(),
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use super::platform::{self, Libc};
+use super::policy;
+use crate::{error::AppResult, state::LoaderFix};
+use anyhow::{Context, anyhow};
+use goblin::elf::Elf;
+use goblin::elf::header::{EM_386, EM_AARCH64, EM_ARM, EM_RISCV, EM_X86_64, machine_to_str};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directories searched when checking whether a needed shared library is present.
+const LIBRARY_DIRS: &[&str] = &["/lib", "/lib64", "/usr/lib", "/usr/lib64", "/usr/local/lib"];
+
+/// The loader's configuration, listing further library directories.
+const LD_SO_CONF: &str = "/etc/ld.so.conf";
+
+/// The parts of an ELF binary that decide whether it can run on this host.
+#[derive(Debug, Clone)]
+pub struct ElfInfo {
+    /// The `e_machine` field of the ELF header.
+    pub machine: u16,
+    pub is_64: bool,
+    /// The dynamic loader requested through `PT_INTERP`. `None` for static binaries.
+    pub interpreter: Option<String>,
+    /// The `DT_NEEDED` shared libraries.
+    pub needed: Vec<String>,
+    /// The `DT_RPATH` and `DT_RUNPATH` entries, in that order, as written in the binary.
+    pub rpath: Vec<String>,
+    /// The directory holding the binary, which `$ORIGIN` in `rpath` stands for.
+    pub origin: PathBuf,
+    /// The newest `GLIBC_2.x` symbol version the binary requires, if any.
+    pub required_glibc: Option<(u32, u32)>,
+}
+
+impl ElfInfo {
+    /// Inspects the file at `path`, following symlinks.
+    /// Returns `Ok(None)` if the file is not an ELF binary (e.g. a script or a Mach-O binary).
+    pub fn read(path: &Path) -> AppResult<Option<Self>> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if !bytes.starts_with(b"\x7fELF") {
+            return Ok(None);
+        }
+        let elf = Elf::parse(&bytes)
+            .with_context(|| format!("Failed to parse ELF binary {}", path.display()))?;
+
+        let required_glibc = elf.verneed.as_ref().and_then(|verneed| {
+            verneed
+                .iter()
+                .flat_map(|need| need.iter().collect::<Vec<_>>())
+                .filter_map(|aux| elf.dynstrtab.get_at(aux.vna_name))
+                // Version-needed entries such as "GLIBC_2.34".
+                .filter_map(|name| policy::parse_major_minor(name.strip_prefix("GLIBC_")?))
+                .max()
+        });
+
+        let real_path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        Ok(Some(Self {
+            machine: elf.header.e_machine,
+            is_64: elf.is_64,
+            interpreter: elf.interpreter.map(str::to_string),
+            needed: elf.libraries.iter().map(|lib| lib.to_string()).collect(),
+            rpath: elf
+                .rpaths
+                .iter()
+                .chain(&elf.runpaths)
+                .flat_map(|entry| entry.split(':'))
+                .filter(|dir| !dir.is_empty())
+                .map(str::to_string)
+                .collect(),
+            origin: real_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            required_glibc,
+        }))
+    }
+
+    /// A human-readable name for the machine type, e.g. "x86_64".
+    pub fn machine_name(&self) -> &'static str {
+        machine_to_str(self.machine)
+    }
+
+    /// The needed libraries that cannot be found where the loader would look: the binary's
+    /// `RPATH`/`RUNPATH`, `LD_LIBRARY_PATH`, the directories in `ld.so.conf` and the usual
+    /// library directories, including their multiarch subdirectories.
+    fn missing_libraries(&self) -> Vec<&str> {
+        let dirs = self.library_search_dirs();
+        self.needed
+            .iter()
+            .map(String::as_str)
+            .filter(|lib| !library_exists(lib, &dirs))
+            .collect()
+    }
+
+    /// The directories the loader searches for this binary's libraries, in no particular order.
+    fn library_search_dirs(&self) -> Vec<PathBuf> {
+        let origin = self.origin.to_string_lossy();
+        let mut dirs: Vec<PathBuf> = self
+            .rpath
+            .iter()
+            .map(|dir| {
+                PathBuf::from(
+                    dir.replace("${ORIGIN}", &origin)
+                        .replace("$ORIGIN", &origin),
+                )
+            })
+            .collect();
+        if let Some(paths) = env::var_os("LD_LIBRARY_PATH") {
+            dirs.extend(env::split_paths(&paths));
+        }
+        dirs.extend(ld_so_conf_dirs(Path::new(LD_SO_CONF)));
+        for dir in LIBRARY_DIRS {
+            let dir = Path::new(dir);
+            dirs.push(dir.to_path_buf());
+            dirs.extend(multiarch_triplets().iter().map(|triplet| dir.join(triplet)));
+        }
+        dirs
+    }
+
+    /// Checks whether this binary can run on the host, returning the reason if not.
+    pub fn check_runnable(&self) -> Result<(), String> {
+        let host_machine = host_machine().ok_or_else(|| {
+            format!(
+                "unsupported host architecture {} for ELF binaries",
+                env::consts::ARCH
+            )
+        })?;
+        if self.machine != host_machine {
+            return Err(format!(
+                "built for {}, but this system is {}",
+                self.machine_name(),
+                machine_to_str(host_machine)
+            ));
+        }
+        if self.is_64 != cfg!(target_pointer_width = "64") {
+            return Err(format!(
+                "{}-bit binary on a {}-bit system",
+                if self.is_64 { 64 } else { 32 },
+                if self.is_64 { 32 } else { 64 }
+            ));
+        }
+        if let Some(interpreter) = &self.interpreter
+            && !Path::new(interpreter).exists()
+        {
+            return Err(format!("its interpreter {} does not exist", interpreter));
+        }
+        if let Some(required) = self.required_glibc {
+            match platform::host_libc() {
+                libc @ Libc::Glibc { .. } if !libc.supports_glibc(required) => {
+                    return Err(format!(
+                        "requires glibc {}.{}, which is newer than the system's",
+                        required.0, required.1
+                    ));
+                }
+                Libc::Glibc { .. } => {}
+                libc => return Err(format!("requires glibc, but the system uses {}", libc)),
+            }
+        }
+        let missing = self.missing_libraries();
+        if !missing.is_empty() {
+            return Err(format!(
+                "needs {}, which the system does not have",
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The ELF machine type matching the architecture isoterm was built for.
+fn host_machine() -> Option<u16> {
+    match env::consts::ARCH {
+        "x86_64" => Some(EM_X86_64),
+        "aarch64" => Some(EM_AARCH64),
+        "x86" => Some(EM_386),
+        "arm" => Some(EM_ARM),
+        "riscv64" => Some(EM_RISCV),
+        _ => None,
+    }
+}
+
+fn library_exists(lib: &str, dirs: &[PathBuf]) -> bool {
+    if lib.contains('/') {
+        return Path::new(lib).exists();
+    }
+    dirs.iter().any(|dir| dir.join(lib).exists())
+}
+
+/// The Debian-style multiarch directory names for the architecture isoterm was built for,
+/// e.g. `lib/x86_64-linux-gnu`. They do not always start with the Rust architecture name.
+fn multiarch_triplets() -> &'static [&'static str] {
+    match env::consts::ARCH {
+        "x86_64" => &["x86_64-linux-gnu"],
+        "x86" => &["i386-linux-gnu"],
+        "aarch64" => &["aarch64-linux-gnu"],
+        "arm" => &["arm-linux-gnueabihf", "arm-linux-gnueabi"],
+        "riscv64" => &["riscv64-linux-gnu"],
+        _ => &[],
+    }
+}
+
+/// The library directories listed in the `ld.so.conf` at `path`, following `include` lines.
+/// Only `*` at the start of the file name part of an include pattern is understood, which
+/// covers the usual `include /etc/ld.so.conf.d/*.conf`.
+fn ld_so_conf_dirs(path: &Path) -> Vec<PathBuf> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut dirs = Vec::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(pattern) = line.strip_prefix("include") {
+            for include in expand_include(pattern.trim(), path) {
+                dirs.extend(ld_so_conf_dirs(&include));
+            }
+        } else if !line.is_empty() {
+            dirs.push(PathBuf::from(line));
+        }
+    }
+    dirs
+}
+
+/// The files an `include` pattern in the `ld.so.conf` at `conf` refers to.
+fn expand_include(pattern: &str, conf: &Path) -> Vec<PathBuf> {
+    let pattern = match conf.parent() {
+        Some(parent) => parent.join(pattern),
+        None => PathBuf::from(pattern),
+    };
+    let (Some(dir), Some(name)) = (pattern.parent(), pattern.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy();
+    let Some(suffix) = name.strip_prefix('*') else {
+        return vec![pattern.clone()];
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|file| file.to_string_lossy().ends_with(suffix))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Points a binary's `PT_INTERP` at `loader` and adds the loader's directory, where the
+/// host's libc lives, to its `RPATH`. Existing `RPATH` entries, e.g. for libraries shipped
+/// next to the binary, are kept. This uses `patchelf`, which must be on the PATH.
+pub fn patch_interpreter(binary: &Path, loader: &Path) -> AppResult<LoaderFix> {
+    let patchelf = which::which("patchelf")
+        .context("`patchelf` is required to adapt this binary to the system's loader")?;
+    let loader_dir = loader
+        .parent()
+        .ok_or_else(|| anyhow!("Loader path {} has no parent", loader.display()))?
+        .display()
+        .to_string();
+    let mut rpath = ElfInfo::read(binary)?
+        .map(|info| info.rpath)
+        .unwrap_or_default();
+    if !rpath.contains(&loader_dir) {
+        rpath.push(loader_dir);
+    }
+    let rpath = rpath.join(":");
+
+    let output = Command::new(patchelf)
+        .arg("--set-interpreter")
+        .arg(loader)
+        .arg("--set-rpath")
+        .arg(&rpath)
+        .arg(binary)
+        .output()
+        .context("Failed to execute patchelf")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "patchelf failed on {}: {}",
+            binary.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    tracing::info!(binary = %binary.display(), loader = %loader.display(), "Patched interpreter");
+    Ok(LoaderFix::PatchedInterpreter {
+        interpreter: loader.display().to_string(),
+        rpath,
+    })
+}
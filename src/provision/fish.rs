@@ -1,15 +1,15 @@
 use super::{
-    ArchiveType, ProvisionContext, Tool, create_symlink, download_to_temp_file,
-    extract_full_archive, find_github_release_asset_url, provision_source_share,
+    ExtractionStrategy, ProvisionContext, Tool, provision_from_github_release,
+    provision_source_share,
 };
-use crate::error::AppResult;
-use console::style;
+use crate::{error::AppResult, state::Provenance};
+use async_trait::async_trait;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::env;
-use std::fs;
+use std::path::{Path, PathBuf};
 
 pub struct Fish;
 
+#[async_trait]
 impl Tool for Fish {
     fn name(&self) -> &'static str {
         "fish"
@@ -23,43 +23,66 @@ impl Tool for Fish {
         "fish"
     }
 
+    fn min_version(&self) -> Option<&'static str> {
+        // The shell integrations generated by atuin and zoxide target fish 3.4+.
+        Some("3.4")
+    }
+
+    fn install_dirs(&self) -> Vec<String> {
+        vec!["fish_runtime".to_string()]
+    }
+
+    fn companion_dirs(&self) -> Vec<String> {
+        vec!["fish_runtime/share".to_string()]
+    }
+
+    fn vendor_dirs(&self, system_path: &Path) -> Vec<(PathBuf, String)> {
+        // e.g. /usr/bin/fish -> /usr/share/fish, which holds what the release's `share` does.
+        let Some(prefix) = system_path.parent().and_then(Path::parent) else {
+            return Vec::new();
+        };
+        vec![(
+            prefix.join("share").join("fish"),
+            "fish_runtime/share".to_string(),
+        )]
+    }
+
+    fn asset_os_keywords(&self) -> Option<&'static [&'static str]> {
+        // e.g. `fish-4.0.2-linux-x86_64.tar.xz`
+        Some(&["linux"])
+    }
+
+    fn asset_extension(&self, _os: &str) -> &'static str {
+        "tar.xz"
+    }
+
+    fn provides(&self) -> Vec<String> {
+        vec!["fish-share".to_string()]
+    }
+
+    fn data_dirs(&self) -> Vec<String> {
+        // fish's standard library functions and completions, which it finds through
+        // `XDG_DATA_DIRS`. Distinct from `XDG_DATA_HOME`, which isolates user data.
+        vec!["fish_runtime/share".to_string()]
+    }
+
     #[tracing::instrument(skip(self, context, pb, spinner_style), fields(tool = self.name()))]
     async fn provision_from_source(
         &self,
         context: &ProvisionContext,
         pb: &ProgressBar,
         spinner_style: &ProgressStyle,
-    ) -> AppResult<()> {
+    ) -> AppResult<Provenance> {
         // --- Fish-specific download and extraction ---
-        pb.set_message(format!("Downloading {}...", style(self.name()).bold()));
-        let (download_url, asset_name) = find_github_release_asset_url(
-            self.name(),
-            self.repo(),
-            "https://api.github.com",
-            env::consts::OS,
-            env::consts::ARCH,
-            &context.client,
-        )
-        .await?;
-        let temp_file =
-            download_to_temp_file(&download_url, &asset_name, pb, &context.client).await?;
-        let file = temp_file.reopen()?;
-
-        pb.set_style(spinner_style.clone());
-        pb.set_message(format!(
-            "Extracting archive for {}...",
-            style(self.name()).bold()
-        ));
-
+        // The whole archive goes into `fish_runtime`, and each candidate asset is
+        // smoke-tested before it is accepted, like any other release download.
+        let strategy = ExtractionStrategy::FullArchive {
+            dir: "fish_runtime",
+            path_in_archive: self.binary_name(),
+        };
+        let provenance =
+            provision_from_github_release(self, context, strategy, pb, spinner_style).await?;
         let fish_runtime_dir = context.env_dir.join("fish_runtime");
-        fs::create_dir_all(&fish_runtime_dir)?;
-
-        let archive_type = ArchiveType::from_asset_name(&asset_name)?;
-        extract_full_archive(file, archive_type, &fish_runtime_dir)?;
-
-        let binary_path_in_archive = fish_runtime_dir.join(self.binary_name());
-        let tool_path_in_env = context.env_dir.join("bin").join(self.binary_name());
-        create_symlink(&binary_path_in_archive, &tool_path_in_env)?;
 
         // --- Fish-specific 'share' directory provisioning ---
         // This is necessary because some release archives (like for macOS) don't
@@ -77,6 +100,6 @@ impl Tool for Fish {
             tracing::debug!("'share' directory already exists, skipping download.");
         }
 
-        Ok(())
+        Ok(provenance)
     }
 }
@@ -0,0 +1,268 @@
+use super::Tool;
+use crate::error::AppResult;
+use crate::shell::Shell;
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+/// How long a cancelled step may take to wind down on its own before it is dropped.
+/// Downloads stop within a chunk, but a request stuck connecting never returns.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// Something a tool needs in the environment before it can be provisioned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dependency {
+    /// Another tool, by name.
+    Tool(String),
+    /// A resource provided by some tool, e.g. "fish-share". See [`Tool::provides`].
+    Resource(String),
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dependency::Tool(name) => write!(f, "tool '{}'", name),
+            Dependency::Resource(name) => write!(f, "resource '{}'", name),
+        }
+    }
+}
+
+/// How a single tool's step in the graph ended.
+pub enum StepOutcome<T> {
+    Done(T),
+    Failed(anyhow::Error),
+    /// Not attempted because a tool it depends on (directly or transitively) did not succeed.
+    Blocked {
+        dependency: String,
+    },
+    /// Not attempted because the run was cancelled first.
+    Cancelled,
+}
+
+/// The selected tools and the dependency edges between them.
+pub struct ProvisionGraph {
+    tools: Vec<Arc<dyn Tool>>,
+    /// For each tool, the indices of the tools it depends on.
+    dependencies: Vec<Vec<usize>>,
+}
+
+impl ProvisionGraph {
+    /// Resolves every tool's dependencies in an environment running `shell` against the
+    /// other `tools`, rejecting unsatisfiable dependencies, resources provided by more than
+    /// one tool, and cycles.
+    pub fn new(tools: Vec<Arc<dyn Tool>>, shell: Shell) -> AppResult<Self> {
+        let mut providers: HashMap<String, usize> = HashMap::new();
+        for (index, tool) in tools.iter().enumerate() {
+            for resource in tool.provides() {
+                if let Some(&other) = providers.get(&resource) {
+                    return Err(anyhow!(
+                        "{} and {} both provide resource '{}'",
+                        tools[other].name(),
+                        tool.name(),
+                        resource
+                    ));
+                }
+                providers.insert(resource, index);
+            }
+        }
+
+        let mut dependencies = Vec::with_capacity(tools.len());
+        for tool in &tools {
+            let mut edges = Vec::new();
+            for dependency in tool.dependencies(shell) {
+                let provider = match &dependency {
+                    Dependency::Tool(name) => tools.iter().position(|t| t.name() == name),
+                    Dependency::Resource(name) => providers.get(name).copied(),
+                };
+                let provider = provider.ok_or_else(|| {
+                    anyhow!(
+                        "{} depends on {}, which none of the selected tools provides",
+                        tool.name(),
+                        dependency
+                    )
+                })?;
+                if !edges.contains(&provider) {
+                    edges.push(provider);
+                }
+            }
+            dependencies.push(edges);
+        }
+
+        let graph = Self {
+            tools,
+            dependencies,
+        };
+        graph.check_acyclic()?;
+        Ok(graph)
+    }
+
+    pub fn tools(&self) -> &[Arc<dyn Tool>] {
+        &self.tools
+    }
+
+    /// Kahn's algorithm: if some tools never reach in-degree zero, they are on a cycle or
+    /// depend on one.
+    fn check_acyclic(&self) -> AppResult<()> {
+        let mut in_degree: Vec<usize> = self.dependencies.iter().map(Vec::len).collect();
+        let mut ready: Vec<usize> = (0..self.tools.len())
+            .filter(|&i| in_degree[i] == 0)
+            .collect();
+        let mut visited = 0;
+        while let Some(index) = ready.pop() {
+            visited += 1;
+            for dependent in self.dependents(index) {
+                in_degree[dependent] -= 1;
+                if in_degree[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+        if visited == self.tools.len() {
+            return Ok(());
+        }
+
+        // Every remaining tool still waits on a remaining dependency, so following those from
+        // any of them must come back around. Only the tools on that loop are the cycle.
+        let mut path = vec![(0..self.tools.len()).find(|&i| in_degree[i] > 0).unwrap()];
+        let start = loop {
+            let current = *path.last().unwrap();
+            let next = self.dependencies[current]
+                .iter()
+                .copied()
+                .find(|&dependency| in_degree[dependency] > 0)
+                .unwrap();
+            if let Some(start) = path.iter().position(|&index| index == next) {
+                break start;
+            }
+            path.push(next);
+        };
+        let mut cycle: Vec<&str> = path[start..]
+            .iter()
+            .map(|&index| self.tools[index].name())
+            .collect();
+        cycle.push(cycle[0]);
+        Err(anyhow!(
+            "Tool dependencies form a cycle: {}",
+            cycle.join(" -> ")
+        ))
+    }
+
+    /// The tools that depend directly on the tool at `index`.
+    fn dependents(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        self.dependencies
+            .iter()
+            .enumerate()
+            .filter(move |(_, edges)| edges.contains(&index))
+            .map(|(dependent, _)| dependent)
+    }
+
+    /// Runs `step` for every tool as soon as all of its dependencies have succeeded,
+    /// with as many steps in flight at once as the graph allows. Once `cancel` fires,
+    /// no further steps are started; steps in flight are expected to watch it themselves.
+    /// Returns one outcome per tool, in the order the tools were given.
+    pub async fn run<T, F, Fut>(&self, cancel: &CancellationToken, step: F) -> Vec<StepOutcome<T>>
+    where
+        T: Send + 'static,
+        F: Fn(Arc<dyn Tool>) -> Fut,
+        Fut: Future<Output = AppResult<T>> + Send + 'static,
+    {
+        let mut outcomes: Vec<Option<StepOutcome<T>>> =
+            (0..self.tools.len()).map(|_| None).collect();
+        let mut pending: Vec<usize> = self.dependencies.iter().map(Vec::len).collect();
+        let mut tasks = JoinSet::new();
+        let mut task_ids = HashMap::new();
+
+        let start = |index: usize,
+                     tasks: &mut JoinSet<AppResult<T>>,
+                     task_ids: &mut HashMap<_, _>| {
+            if cancel.is_cancelled() {
+                return;
+            }
+            tracing::debug!(tool = self.tools[index].name(), "Starting step");
+            let handle = tasks.spawn(cancellable(step(self.tools[index].clone()), cancel.clone()));
+            task_ids.insert(handle.id(), index);
+        };
+        for index in (0..self.tools.len()).filter(|&i| pending[i] == 0) {
+            start(index, &mut tasks, &mut task_ids);
+        }
+
+        while let Some(joined) = tasks.join_next_with_id().await {
+            let (index, result) = match joined {
+                Ok((id, result)) => (task_ids[&id], result),
+                Err(e) => (
+                    task_ids[&e.id()],
+                    Err(anyhow!(
+                        "The provisioning task panicked or was cancelled: {}",
+                        e
+                    )),
+                ),
+            };
+
+            match result {
+                Ok(value) => {
+                    outcomes[index] = Some(StepOutcome::Done(value));
+                    for dependent in self.dependents(index).collect::<Vec<_>>() {
+                        pending[dependent] -= 1;
+                        if pending[dependent] == 0 && outcomes[dependent].is_none() {
+                            start(dependent, &mut tasks, &mut task_ids);
+                        }
+                    }
+                }
+                Err(e) => {
+                    outcomes[index] = Some(StepOutcome::Failed(e));
+                    self.block_dependents(index, self.tools[index].name(), &mut outcomes);
+                }
+            }
+        }
+
+        outcomes
+            .into_iter()
+            .map(|outcome| outcome.unwrap_or(StepOutcome::Cancelled))
+            .collect()
+    }
+
+    /// Marks everything downstream of the failed tool at `index` as blocked by `failed`.
+    fn block_dependents<T>(
+        &self,
+        index: usize,
+        failed: &str,
+        outcomes: &mut [Option<StepOutcome<T>>],
+    ) {
+        for dependent in self.dependents(index).collect::<Vec<_>>() {
+            if outcomes[dependent].is_none() {
+                tracing::debug!(
+                    tool = self.tools[dependent].name(),
+                    dependency = failed,
+                    "Blocked by failed dependency"
+                );
+                outcomes[dependent] = Some(StepOutcome::Blocked {
+                    dependency: failed.to_string(),
+                });
+                self.block_dependents(dependent, failed, outcomes);
+            }
+        }
+    }
+}
+
+/// Lets `step` react to `cancel` itself, and drops it if it has not finished within
+/// [`CANCEL_GRACE_PERIOD`].
+async fn cancellable<T>(
+    step: impl Future<Output = AppResult<T>>,
+    cancel: CancellationToken,
+) -> AppResult<T> {
+    tokio::pin!(step);
+    tokio::select! {
+        result = &mut step => result,
+        _ = cancel.cancelled() => {
+            match tokio::time::timeout(CANCEL_GRACE_PERIOD, &mut step).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow!("Did not stop within {:?} of being cancelled", CANCEL_GRACE_PERIOD)),
+            }
+        }
+    }
+}
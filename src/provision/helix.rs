@@ -1,13 +1,15 @@
 use super::{ProvisionContext, Tool, provision_helix_runtime_for_symlink};
-use crate::error::AppResult;
+use crate::{activation::EnvVar, config::write_config_file, error::AppResult};
 use anyhow::Context;
+use async_trait::async_trait;
 use indicatif::ProgressBar;
 use shellexpand;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::task;
 
 pub struct Helix;
 
+#[async_trait]
 impl Tool for Helix {
     fn name(&self) -> &'static str {
         "helix"
@@ -21,6 +23,50 @@ impl Tool for Helix {
         "hx"
     }
 
+    fn min_version(&self) -> Option<&'static str> {
+        // `end-of-line-diagnostics` and `inline-diagnostics` in our config.toml need 25.01.
+        Some("25.01")
+    }
+
+    fn env_vars(&self) -> Vec<EnvVar> {
+        // Only set if the runtime was provisioned into the env. Otherwise a system
+        // Helix falls back to its user-wide runtime.
+        vec![EnvVar::path("HELIX_RUNTIME", "helix/runtime").if_exists()]
+    }
+
+    fn companion_dirs(&self) -> Vec<String> {
+        vec!["helix/runtime".to_string()]
+    }
+
+    fn vendor_dirs(&self, system_path: &Path) -> Vec<(PathBuf, String)> {
+        // Release archives keep the runtime next to `hx`; distributions move it under the prefix.
+        let Some(bin_dir) = system_path.parent() else {
+            return Vec::new();
+        };
+        let mut candidates = vec![bin_dir.join("runtime")];
+        if let Some(prefix) = bin_dir.parent() {
+            candidates.push(prefix.join("lib").join("helix").join("runtime"));
+            candidates.push(prefix.join("share").join("helix").join("runtime"));
+        }
+        candidates
+            .into_iter()
+            .map(|dir| (dir, "helix/runtime".to_string()))
+            .collect()
+    }
+
+    fn asset_os_keywords(&self) -> Option<&'static [&'static str]> {
+        // e.g. `helix-25.01-x86_64-linux.tar.xz`
+        Some(&["linux"])
+    }
+
+    fn asset_extension(&self, os: &str) -> &'static str {
+        match os {
+            "linux" | "android" => "tar.xz",
+            "macos" => "zip",
+            _ => "tar.gz",
+        }
+    }
+
     fn path_in_archive(&self) -> Option<&'static str> {
         // The binary is at the root of the archive, e.g., "helix-23.10-x86_64-linux/hx"
         // The top-level directory is stripped during extraction.
@@ -31,6 +77,23 @@ impl Tool for Helix {
     // It will fall back to the default `Tool` trait's implementation,
     // which correctly handles the `FullArchive` strategy because `path_in_archive` returns `Some`.
 
+    #[tracing::instrument(skip_all, fields(tool = self.name()))]
+    async fn configure(&self, context: &ProvisionContext) -> AppResult<()> {
+        let config_toml_content = include_str!("../../templates/helix/config.toml");
+        write_config_file(
+            &context.env_dir,
+            "config/helix/config.toml",
+            config_toml_content,
+        )?;
+
+        let languages_toml_content = include_str!("../../templates/helix/languages.toml");
+        write_config_file(
+            &context.env_dir,
+            "config/helix/languages.toml",
+            languages_toml_content,
+        )
+    }
+
     #[tracing::instrument(skip(self, context, pb, system_path), fields(tool = self.name()))]
     async fn post_symlink_hook(
         &self,
@@ -38,10 +101,16 @@ impl Tool for Helix {
         pb: &ProgressBar,
         system_path: &Path,
     ) -> AppResult<()> {
+        // A vendored Helix may have brought its runtime along already.
+        if context.env_dir.join("helix").join("runtime").is_dir() {
+            tracing::debug!("Helix runtime already in the environment.");
+            return Ok(());
+        }
+
         let user_helix_runtime_dir = shellexpand::tilde("~/.config/helix/runtime").to_string();
         if !Path::new(&user_helix_runtime_dir).exists() {
             tracing::debug!("User-wide helix runtime not found. Provisioning a local one.");
-            pb.println(" › Detected Helix symlink without a user-wide runtime. Provisioning a local runtime to match the system binary's version...".to_string());
+            pb.println(" › Detected Helix symlink without a user-wide runtime. Provisioning a local runtime to match the system binary's version...");
 
             let system_path_clone = system_path.to_path_buf();
             let env_dir_clone = context.env_dir.to_path_buf();
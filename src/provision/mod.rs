@@ -1,24 +1,38 @@
+use crate::activation::{Activation, EnvVar};
 use crate::error::AppResult;
+use crate::interrupt;
+use crate::provision::elf::ElfInfo;
+use crate::provision::graph::Dependency;
+use crate::provision::platform::{HostLayout, Libc};
+use crate::provision::policy::{SourcePolicies, SourcePolicy};
+use crate::shell::Shell;
+use crate::shim;
+use crate::state::{self, LoaderFix, Provenance, Source};
 use anyhow::{Context, anyhow};
+use async_trait::async_trait;
 use console::style;
 use flate2::read::GzDecoder;
 use futures_util::StreamExt;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use pathdiff;
 use regex::Regex;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
 use std::fs::{self, File};
 use std::io::{self, Read, Seek, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::Arc;
-use std::thread;
 use std::time::Duration;
 use tar::Archive;
 use tempfile::NamedTempFile;
-use tokio_retry::Retry;
+use tokio::process::Command as AsyncCommand;
+use tokio::sync::mpsc;
+use tokio::task;
 use tokio_retry::strategy::{ExponentialBackoff, jitter};
+use tokio_retry::{Retry, RetryIf};
 use xz2::read::XzDecoder;
 use zip::ZipArchive;
 
@@ -27,53 +41,166 @@ use std::os::unix::fs::{PermissionsExt, symlink};
 #[cfg(windows)]
 use std::os::windows::fs::{symlink_dir, symlink_file};
 
+/// How long a smoke test of a downloaded binary may run before it counts as failed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How many downloaded chunks may be buffered ahead of a streaming extractor.
+const STREAM_CHANNEL_CAPACITY: usize = 16;
+
 // --- Module Declarations ---
 pub mod atuin;
+pub mod declared;
+pub mod elf;
 pub mod fish;
+pub mod graph;
 pub mod helix;
+pub mod nushell;
+pub mod platform;
+pub mod policy;
+pub mod registry;
 pub mod ripgrep;
 pub mod starship;
 pub mod zoxide;
+pub mod zsh;
 
 // --- Tool Trait ---
+#[async_trait]
 pub trait Tool: Send + Sync {
-    fn name(&self) -> &'static str;
-    fn repo(&self) -> &'static str;
-    fn binary_name(&self) -> &'static str;
+    fn name(&self) -> &str;
+    fn repo(&self) -> &str;
+    fn binary_name(&self) -> &str;
 
     /// The path of the binary within the downloaded archive, if it's not at the root.
-    fn path_in_archive(&self) -> Option<&'static str> {
+    fn path_in_archive(&self) -> Option<&str> {
+        None
+    }
+
+    /// The minimum glibc version this tool's GNU builds are linked against, if any.
+    /// On hosts with an older glibc, the musl build is preferred for this tool only.
+    fn min_glibc(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// The oldest version of the tool that works with the configuration isoterm generates.
+    /// Older system binaries are ignored in favor of a download.
+    fn min_version(&self) -> Option<&str> {
         None
     }
 
+    /// Arguments for a quick smoke test run against a freshly downloaded binary.
+    /// A non-zero exit means the build cannot run on this host (e.g. a missing
+    /// `GLIBC_2.xx` symbol), and the next asset candidate is tried instead.
+    fn probe_args(&self) -> Vec<&str> {
+        vec!["--version"]
+    }
+
+    /// The OS names this tool's Linux and Android release assets use instead of Rust target
+    /// triples, e.g. `linux` in `fish-4.0.2-linux-x86_64.tar.xz`.
+    fn asset_os_keywords(&self) -> Option<&'static [&'static str]> {
+        None
+    }
+
+    /// The archive format of this tool's release assets on `os`. Windows releases are
+    /// always zip files.
+    fn asset_extension(&self, _os: &str) -> &'static str {
+        "tar.gz"
+    }
+
+    /// Describes which release asset this tool needs.
+    fn asset_spec(&self) -> AssetSpec<'_> {
+        AssetSpec {
+            name: self.name(),
+            min_glibc: self.min_glibc(),
+            os_keywords: self.asset_os_keywords(),
+            extension: self.asset_extension(env::consts::OS),
+        }
+    }
+
     /// The main provisioning logic for downloading and installing from a remote source.
     /// The default implementation downloads a single binary from a GitHub release.
     /// More complex tools (like fish, helix) will override this.
+    /// Returns a record of where the installed binary came from.
     #[tracing::instrument(skip(self, context, pb, spinner_style), fields(tool = self.name()))]
     async fn provision_from_source(
         &self,
         context: &ProvisionContext,
         pb: &ProgressBar,
         spinner_style: &ProgressStyle,
-    ) -> AppResult<()> {
+    ) -> AppResult<Provenance> {
         let strategy = if let Some(path_in_archive) = self.path_in_archive() {
-            ExtractionStrategy::FullArchive { path_in_archive }
+            ExtractionStrategy::FullArchive {
+                dir: self.name(),
+                path_in_archive,
+            }
         } else {
             ExtractionStrategy::SingleBinary {
                 binary_name: self.binary_name(),
             }
         };
 
-        provision_from_github_release(
-            context,
-            self.name(),
-            self.repo(),
-            self.binary_name(),
-            strategy,
-            pb,
-            spinner_style,
-        )
-        .await
+        provision_from_github_release(self, context, strategy, pb, spinner_style).await
+    }
+
+    /// Directories inside the environment, besides its `bin/` entry, that hold the tool's files.
+    /// By default, tools whose whole archive is kept own a directory named after them.
+    fn install_dirs(&self) -> Vec<String> {
+        match self.path_in_archive() {
+            Some(_) => vec![self.name().to_string()],
+            None => Vec::new(),
+        }
+    }
+
+    /// Directories inside the environment the tool cannot run without, e.g. its runtime.
+    /// Each is only expected once the directory containing it has been installed.
+    fn companion_dirs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Directories that must be copied along with the system binary at `system_path` when it
+    /// is vendored, each with where it goes in the environment. For a destination listed more
+    /// than once, the first source that exists is used.
+    fn vendor_dirs(&self, _system_path: &Path) -> Vec<(PathBuf, String)> {
+        Vec::new()
+    }
+
+    /// What must be in an environment running `shell` before this tool is provisioned and
+    /// configured.
+    fn dependencies(&self, _shell: Shell) -> Vec<Dependency> {
+        Vec::new()
+    }
+
+    /// Named resources this tool puts into the environment, which other tools can depend on.
+    fn provides(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Code in `shell`'s language that sets the tool up in interactive shells,
+    /// e.g. `starship init fish | source`.
+    fn shell_init(&self, _shell: Shell) -> Option<String> {
+        None
+    }
+
+    /// Environment variables the tool needs in the activated environment.
+    fn env_vars(&self) -> Vec<EnvVar> {
+        Vec::new()
+    }
+
+    /// Directories inside the environment, besides `bin`, to put on the `PATH`.
+    fn path_dirs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Directories inside the environment to prepend to `XDG_DATA_DIRS`.
+    fn data_dirs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Writes the tool's configuration into the environment. Runs once the tool's
+    /// binary is in place, whether it was just provisioned or already there.
+    #[tracing::instrument(skip_all, fields(tool = self.name()))]
+    async fn configure(&self, _context: &ProvisionContext) -> AppResult<()> {
+        // Default is to do nothing.
+        Ok(())
     }
 
     /// A hook that runs after a symlink is created to a system-provided tool.
@@ -91,22 +218,37 @@ pub trait Tool: Send + Sync {
     }
 }
 
+/// The outcome of provisioning a single tool.
+pub struct Provisioned {
+    /// Where the binary came from, or `None` if it was already in the environment.
+    pub provenance: Option<Provenance>,
+    /// What the tool contributes to the activated environment.
+    pub activation: Activation,
+}
+
 /// A context struct to pass shared, read-only data to provisioning tasks.
 #[derive(Clone)]
 pub struct ProvisionContext {
     pub env_dir: PathBuf,
     pub client: reqwest::Client,
+    pub policies: Arc<SourcePolicies>,
+    /// The environment's interactive shell.
+    pub shell: Shell,
+    /// Where the tools already in the environment came from, as recorded by earlier runs.
+    pub recorded: Arc<BTreeMap<String, Provenance>>,
 }
 
 // --- Generic Provisioning Orchestrator ---
 
+/// Provisions a single tool into the environment.
+/// Returns where the tool came from, or `None` if it was already provisioned.
 #[tracing::instrument(skip(tool, context, mp, overall_pb), fields(tool = tool.name()))]
-pub async fn provision_tool<T: Tool>(
-    tool: T,
+pub async fn provision_tool(
+    tool: Arc<dyn Tool>,
     context: ProvisionContext,
     mp: MultiProgress,
     overall_pb: Arc<ProgressBar>,
-) -> AppResult<()> {
+) -> AppResult<Provisioned> {
     let pb = mp.add(ProgressBar::new_spinner());
     pb.enable_steady_tick(Duration::from_millis(120));
     let spinner_style =
@@ -117,194 +259,863 @@ pub async fn provision_tool<T: Tool>(
     let bin_dir = context.env_dir.join("bin");
     let tool_path_in_env = bin_dir.join(tool.binary_name());
 
-    // 1. Check if the binary is already provisioned in our environment.
+    let policy = context.policies.policy_for(tool.name());
+    let min_version = context
+        .policies
+        .min_version_for(tool.name(), tool.min_version());
+    tracing::debug!(?policy, ?min_version, "Resolved source policy");
+
+    // 1. Check if the binary is already provisioned in our environment, from a source
+    //    that this run's policy and minimum version still accept.
     if tool_path_in_env.exists() {
-        tracing::debug!(path = %tool_path_in_env.display(), "Tool already exists, skipping provisioning.");
-        overall_pb.println(format!(
-            "{} {} is already provisioned",
+        let recorded = context.recorded.get(tool.name());
+        match check_installed(
+            &context.policies,
+            policy,
+            min_version,
+            recorded,
+            &tool_path_in_env,
+        ) {
+            Ok(()) => {
+                tracing::debug!(path = %tool_path_in_env.display(), "Tool already exists, skipping provisioning.");
+                configure_tool(tool.as_ref(), &context, &pb, None).await?;
+                overall_pb.println(format!(
+                    "{} {} is already provisioned",
+                    style("✓").green(),
+                    style(tool.name()).bold()
+                ));
+                overall_pb.inc(1);
+                pb.finish_and_clear();
+                return Ok(Provisioned {
+                    provenance: None,
+                    activation: Activation::of(tool.as_ref(), context.shell),
+                });
+            }
+            Err(reason) => {
+                tracing::info!(%reason, "Re-provisioning tool");
+                pb.println(format!(
+                    " › Re-provisioning {}: {}",
+                    style(tool.name()).bold(),
+                    reason
+                ));
+                remove_tool_files(tool.as_ref(), &context.env_dir)?;
+            }
+        }
+    }
+
+    // 2. Check if a usable build of the tool is available on the system PATH,
+    //    unless the tool's source policy rules out system binaries.
+    let system_path = if policy.allows_system() {
+        find_usable_system_binary(
+            tool.name(),
+            tool.binary_name(),
+            min_version,
+            &context.env_dir,
+            &pb,
+        )
+    } else {
+        None
+    };
+
+    // 3. Pick the source according to the policy.
+    let provenance = match (policy, system_path) {
+        (SourcePolicy::PreferSystem | SourcePolicy::SystemOnly, Some(system_path)) => {
+            link_system_binary(
+                tool.as_ref(),
+                &context,
+                &pb,
+                &system_path,
+                &tool_path_in_env,
+            )
+            .await?
+        }
+        (SourcePolicy::SystemOnly, None) => {
+            return Err(anyhow!(
+                "{} is set to system-only, but no usable {} was found on the system PATH",
+                tool.name(),
+                tool.binary_name()
+            ));
+        }
+        (SourcePolicy::PreferDownload, Some(system_path)) => {
+            match tool
+                .provision_from_source(&context, &pb, &spinner_style)
+                .await
+            {
+                Ok(provenance) => provenance,
+                Err(e) => {
+                    interrupt::check()?;
+                    tracing::warn!("Download failed, falling back to system binary: {:#}", e);
+                    pb.println(format!(
+                        " › Could not download {} ({}), using the system binary instead",
+                        style(tool.name()).bold(),
+                        e
+                    ));
+                    if tool_path_in_env.symlink_metadata().is_ok() {
+                        fs::remove_file(&tool_path_in_env)?;
+                    }
+                    pb.set_style(spinner_style.clone());
+                    link_system_binary(
+                        tool.as_ref(),
+                        &context,
+                        &pb,
+                        &system_path,
+                        &tool_path_in_env,
+                    )
+                    .await?
+                }
+            }
+        }
+        _ => {
+            tool.provision_from_source(&context, &pb, &spinner_style)
+                .await?
+        }
+    };
+
+    configure_tool(tool.as_ref(), &context, &pb, Some(&provenance)).await?;
+
+    let message = match &provenance.source {
+        Source::System { path } => format!(
+            "{} Symlinked {} from {}",
+            style("✓").green(),
+            style(tool.name()).bold(),
+            style(path).cyan()
+        ),
+        Source::Vendored { path, .. } => format!(
+            "{} Copied {} from {}",
+            style("✓").green(),
+            style(tool.name()).bold(),
+            style(path).cyan()
+        ),
+        Source::Download { .. } => format!(
+            "{} {} provisioned successfully",
             style("✓").green(),
             style(tool.name()).bold()
-        ));
-        overall_pb.inc(1);
-        pb.finish_and_clear();
+        ),
+    };
+    overall_pb.println(message);
+    overall_pb.inc(1);
+    pb.finish_and_clear();
+
+    Ok(Provisioned {
+        provenance: Some(provenance),
+        activation: Activation::of(tool.as_ref(), context.shell),
+    })
+}
+
+/// Checks that the copy of a tool already at `binary` in the environment, which came from
+/// `recorded`, satisfies this run's source policies and minimum version. Without a record
+/// of where it came from, it is accepted as it is.
+fn check_installed(
+    policies: &SourcePolicies,
+    policy: SourcePolicy,
+    min_version: Option<&str>,
+    recorded: Option<&Provenance>,
+    binary: &Path,
+) -> Result<(), String> {
+    let Some(recorded) = recorded else {
         return Ok(());
+    };
+    match (&recorded.source, policy) {
+        (Source::System { .. } | Source::Vendored { .. }, SourcePolicy::DownloadOnly) => {
+            return Err("it came from the system, but its source is download-only".to_string());
+        }
+        (Source::Download { .. }, SourcePolicy::SystemOnly) => {
+            return Err("it was downloaded, but its source is system-only".to_string());
+        }
+        (Source::System { .. }, _) if policies.vendor_system => {
+            return Err("it is symlinked, but system binaries are to be copied".to_string());
+        }
+        _ => {}
     }
+    // Like the lookup on the PATH, the minimum version only applies to system binaries.
+    if let (Source::System { .. } | Source::Vendored { .. }, Some(min_version)) =
+        (&recorded.source, min_version)
+    {
+        check_min_version(binary, min_version)?;
+    }
+    Ok(())
+}
+
+/// Removes everything a tool installed into the environment: its `bin/` entry, the binary
+/// behind its shim and its install dirs.
+#[tracing::instrument(skip_all, fields(tool = tool.name()))]
+pub fn remove_tool_files(tool: &dyn Tool, env_dir: &Path) -> AppResult<()> {
+    let binary = env_dir.join("bin").join(tool.binary_name());
+    if binary.symlink_metadata().is_ok() {
+        fs::remove_file(&binary)
+            .with_context(|| format!("Failed to remove {}", binary.display()))?;
+    }
+    let real_binary = env_dir.join(shim::LIBEXEC_DIR).join(tool.binary_name());
+    if real_binary.symlink_metadata().is_ok() {
+        fs::remove_file(&real_binary)
+            .with_context(|| format!("Failed to remove {}", real_binary.display()))?;
+    }
+    for dir in tool.install_dirs() {
+        let dir = env_dir.join(dir);
+        if dir.symlink_metadata().is_ok() {
+            fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to remove {}", dir.display()))?;
+        }
+    }
+    tracing::debug!("Removed tool files");
+    Ok(())
+}
+
+/// A configure hook that failed after the tool's binary was put in place. The binary is
+/// fine, so it is kept, and the next run only configures the tool again.
+#[derive(Debug)]
+pub struct ConfigureFailed {
+    tool: String,
+    /// Where the binary came from, or `None` if it was already in the environment.
+    pub provenance: Option<Provenance>,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for ConfigureFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to configure {}", self.tool)
+    }
+}
 
-    // 2. Check if the tool is available on the system PATH.
-    if let Ok(system_path) = which::which(tool.binary_name()) {
-        tracing::debug!(path = %system_path.display(), "Found tool on system");
+impl std::error::Error for ConfigureFailed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// Runs the tool's configure hook, reporting a failure against the tool. `provenance` is
+/// where this run got the binary from, if it put one in place.
+async fn configure_tool(
+    tool: &dyn Tool,
+    context: &ProvisionContext,
+    pb: &ProgressBar,
+    provenance: Option<&Provenance>,
+) -> AppResult<()> {
+    pb.set_message(format!("Configuring {}...", style(tool.name()).bold()));
+    tool.configure(context).await.map_err(|error| {
+        ConfigureFailed {
+            tool: tool.name().to_string(),
+            provenance: provenance.cloned(),
+            error,
+        }
+        .into()
+    })
+}
+
+/// Symlinks a system binary into the environment, or copies it with `--vendor-system`,
+/// and runs the tool's post-symlink hook.
+async fn link_system_binary(
+    tool: &dyn Tool,
+    context: &ProvisionContext,
+    pb: &ProgressBar,
+    system_path: &Path,
+    tool_path_in_env: &Path,
+) -> AppResult<Provenance> {
+    tracing::debug!(path = %system_path.display(), "Found tool on system");
+    let provenance = if context.policies.vendor_system {
+        pb.set_message(format!(
+            "Found {}, copying it into the environment...",
+            style(tool.name()).bold()
+        ));
+        vendor_system_binary(tool, &context.env_dir, system_path, tool_path_in_env)?
+    } else {
         pb.set_message(format!(
             "Found {}, creating symlink...",
             style(tool.name()).bold()
         ));
-        create_symlink(&system_path, &tool_path_in_env)?;
+        create_symlink(system_path, tool_path_in_env)?;
+        Provenance::system(system_path)
+    };
 
-        // Run the post-symlink hook (for Helix runtime, etc.)
-        tool.post_symlink_hook(&context, &pb, &system_path).await?;
+    // Run the post-symlink hook (for Helix runtime, etc.)
+    tool.post_symlink_hook(context, pb, system_path).await?;
 
-        overall_pb.println(format!(
-            "{} Symlinked {} from {}",
-            style("✓").green(),
-            style(tool.name()).bold(),
-            style(system_path.display()).cyan()
-        ));
-        overall_pb.inc(1);
-        pb.finish_and_clear();
-        return Ok(());
+    Ok(provenance)
+}
+
+/// Copies a system binary and its vendor dirs into the environment, recording its version and hash.
+#[tracing::instrument(skip_all, fields(tool = tool.name(), path = %system_path.display()))]
+fn vendor_system_binary(
+    tool: &dyn Tool,
+    env_dir: &Path,
+    system_path: &Path,
+    tool_path_in_env: &Path,
+) -> AppResult<Provenance> {
+    fs::copy(system_path, tool_path_in_env).with_context(|| {
+        format!(
+            "Failed to copy {} to {}",
+            system_path.display(),
+            tool_path_in_env.display()
+        )
+    })?;
+
+    for (source, dest) in tool.vendor_dirs(system_path) {
+        let dest = env_dir.join(dest);
+        if dest.exists() || !source.is_dir() {
+            continue;
+        }
+        tracing::debug!(source = %source.display(), dest = %dest.display(), "Copying vendor dir");
+        copy_dir_all(&source, &dest).with_context(|| {
+            format!("Failed to copy {} to {}", source.display(), dest.display())
+        })?;
     }
 
-    // 3. If not found locally or on PATH, provision from source.
-    tool.provision_from_source(&context, &pb, &spinner_style)
-        .await?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(tool_path_in_env)?, &mut hasher)?;
+    let sha256 = hex::encode(hasher.finalize());
+    let version = get_binary_version(tool_path_in_env, "--version")
+        .ok()
+        .and_then(|output| output.lines().next().map(|line| line.trim().to_string()));
+    tracing::debug!(?version, %sha256, "Vendored system binary");
 
-    overall_pb.println(format!(
-        "{} {} provisioned successfully",
-        style("✓").green(),
-        style(tool.name()).bold()
-    ));
-    overall_pb.inc(1);
-    pb.finish_and_clear();
+    Ok(Provenance::vendored(system_path, version, sha256))
+}
 
+/// Recursively copies `source` to `dest`. On Unix, symlinks are copied as they are.
+fn copy_dir_all(source: &Path, dest: &Path) -> AppResult<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dest.join(entry.file_name());
+        #[cfg(unix)]
+        if file_type.is_symlink() {
+            symlink(fs::read_link(entry.path())?, &target)?;
+            continue;
+        }
+        if file_type.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
     Ok(())
 }
 
 // --- Helper Functions ---
 
-/// Attempts to get the system's glibc version.
-/// Returns a tuple of (major, minor) version numbers on success.
-#[cfg(target_os = "linux")]
-fn get_glibc_version() -> Option<(u32, u32)> {
-    let mut child = Command::new("ldd")
-        .arg("--version")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null()) // Redirect stderr to prevent it from blocking
-        .spawn()
-        .ok()?;
+/// Looks up a tool on the system PATH, skipping binaries that cannot run on this
+/// host, such as a build for another architecture or a leftover 32-bit install,
+/// and binaries older than `min_version`. Every match on the PATH is tried in order,
+/// so an unusable binary early on the PATH does not hide a usable one behind it.
+fn find_usable_system_binary(
+    name: &str,
+    binary_name: &str,
+    min_version: Option<&str>,
+    env_dir: &Path,
+    pb: &ProgressBar,
+) -> Option<PathBuf> {
+    find_system_binaries(name, binary_name, env_dir, pb)
+        .into_iter()
+        .find(|system_path| match check_system_binary(system_path, min_version) {
+            Ok(()) => true,
+            Err(reason) => {
+                tracing::info!(path = %system_path.display(), %reason, "Ignoring unusable system binary");
+                pb.println(format!(
+                    " › Ignoring {} at {}: {}",
+                    style(name).bold(),
+                    style(system_path.display()).cyan(),
+                    reason
+                ));
+                false
+            }
+        })
+}
 
-    // Take ownership of the stdout pipe.
-    let mut stdout_pipe = child.stdout.take()?;
+/// Checks that the system binary at `path` can run on this host and is at least `min_version`.
+fn check_system_binary(path: &Path, min_version: Option<&str>) -> Result<(), String> {
+    match ElfInfo::read(path) {
+        Ok(Some(info)) => {
+            tracing::debug!(
+                path = %path.display(),
+                machine = info.machine_name(),
+                interpreter = ?info.interpreter,
+                needed = ?info.needed,
+                "Inspected system binary"
+            );
+            info.check_runnable()?;
+        }
+        // Not an ELF binary, e.g. a wrapper script or a Mach-O binary on macOS.
+        Ok(None) => {}
+        Err(e) => return Err(format!("it could not be inspected ({:#})", e)),
+    }
 
-    // Spawn a thread to read stdout asynchronously. This prevents a deadlock
-    // if the child process generates a lot of output and fills the buffer.
-    let stdout_thread = thread::spawn(move || {
-        let mut stdout = String::new();
-        stdout_pipe.read_to_string(&mut stdout).ok()?;
-        Some(stdout)
-    });
+    if let Some(min_version) = min_version {
+        check_min_version(path, min_version)?;
+    }
+    Ok(())
+}
 
-    // Retrieve the output from the reader thread *before* waiting on the child.
-    // This is crucial to prevent a deadlock where the child fills the stdout
-    // buffer and waits for the parent to read, while the parent is waiting for
-    // the child to exit.
-    let stdout = stdout_thread.join().ok()??;
+/// Looks up every match for a binary on the PATH, in PATH order, and resolves each to the
+/// real file behind any symlinks. PATH entries inside an isoterm environment are skipped, so
+/// that running isoterm from an activated environment does not link the new environment
+/// back into the old one.
+fn find_system_binaries(
+    name: &str,
+    binary_name: &str,
+    env_dir: &Path,
+    pb: &ProgressBar,
+) -> Vec<PathBuf> {
+    let Some(path) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let search_path = env::join_paths(env::split_paths(&path).filter(|dir| {
+        let inside = is_inside_environment(dir, env_dir);
+        if inside {
+            tracing::debug!(dir = %dir.display(), "Skipping PATH entry inside an isoterm environment");
+        }
+        !inside
+    }));
+    let (Ok(search_path), Ok(cwd)) = (search_path, env::current_dir()) else {
+        return Vec::new();
+    };
+    let Ok(candidates) = which::which_in_all(binary_name, Some(search_path), cwd) else {
+        return Vec::new();
+    };
 
-    // Now, wait for the process to complete.
-    let status = child.wait().ok()?;
-    if !status.success() {
-        return None;
+    let mut binaries: Vec<PathBuf> = Vec::new();
+    for candidate in candidates {
+        match resolve_system_binary(&candidate, env_dir) {
+            // e.g. /bin and /usr/bin on a merged-/usr system lead to the same file.
+            Ok(real_path) if binaries.contains(&real_path) => {}
+            Ok(real_path) => {
+                tracing::debug!(path = %candidate.display(), real_path = %real_path.display(), "Resolved system binary");
+                binaries.push(real_path);
+            }
+            Err(reason) => {
+                tracing::warn!(path = %candidate.display(), %reason, "Ignoring system binary");
+                pb.println(format!(
+                    " › Ignoring {} at {}: {}",
+                    style(name).bold(),
+                    style(candidate.display()).cyan(),
+                    reason
+                ));
+            }
+        }
     }
-    let first_line = stdout.lines().next()?;
+    binaries
+}
 
-    // Regex to find version numbers like "2.35"
-    let re = Regex::new(r"(\d+)\.(\d+)").ok()?;
+/// Follows the symlinks at `path` to the real binary, rejecting symlinks that loop
+/// and binaries that live inside an isoterm environment.
+fn resolve_system_binary(path: &Path, env_dir: &Path) -> Result<PathBuf, String> {
+    let real_path = fs::canonicalize(path)
+        .map_err(|e| format!("its symlinks do not lead to a binary ({})", e))?;
+    if is_inside_environment(&real_path, env_dir) {
+        let root = state::environment_containing(&real_path).unwrap_or_else(|| env_dir.into());
+        return Err(format!(
+            "it resolves to {}, inside the isoterm environment at {}",
+            real_path.display(),
+            root.display()
+        ));
+    }
+    Ok(real_path)
+}
 
-    // Find the last match on the first line, as that's typically the version number.
-    let caps = re.captures_iter(first_line).last()?;
+/// Whether `path` is inside `env_dir` or any other isoterm environment.
+fn is_inside_environment(path: &Path, env_dir: &Path) -> bool {
+    // Compare real paths, so that a symlinked or relative PATH entry is caught too.
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let env_dir = fs::canonicalize(env_dir).unwrap_or_else(|_| env_dir.to_path_buf());
+    path.starts_with(&env_dir) || state::environment_containing(&path).is_some()
+}
 
-    let major = caps.get(1)?.as_str().parse().ok()?;
-    let minor = caps.get(2)?.as_str().parse().ok()?;
+/// Checks that the binary at `path` reports at least `min_version` through `--version`.
+fn check_min_version(path: &Path, min_version: &str) -> Result<(), String> {
+    let required = policy::parse_version(min_version)
+        .ok_or_else(|| format!("invalid minimum version '{}'", min_version))?;
+    let output = get_binary_version(path, "--version").map_err(|e| format!("{:#}", e))?;
+    let found = policy::parse_version(&output)
+        .ok_or_else(|| format!("could not parse a version from '{}'", output.trim()))?;
+
+    if policy::compare_versions(&found, &required).is_lt() {
+        let found = found
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+        return Err(format!(
+            "version {} is older than the required {}",
+            found, min_version
+        ));
+    }
+    tracing::debug!(path = %path.display(), ?found, "System binary meets the minimum version");
+    Ok(())
+}
 
-    tracing::debug!("Detected glibc version {}.{}", major, minor);
-    Some((major, minor))
+/// Configures a progress bar to display the progress of a download.
+fn setup_download_progress_bar(
+    pb: &ProgressBar,
+    asset_name: &str,
+    total_size: u64,
+) -> AppResult<()> {
+    let download_style = ProgressStyle::with_template(
+        "{spinner:.green} {msg}\n{wide_bar:.cyan/blue} {bytes}/{total_bytes} ({eta})",
+    )?
+    .progress_chars("#>-");
+
+    pb.set_style(download_style);
+    pb.set_length(total_size);
+    pb.set_message(format!("Downloading {}", style(asset_name).cyan()));
+    Ok(())
 }
 
-/// Manages the state for a file download, including progress bar and temp file.
+/// Manages the state for a file download, including progress bar, digest and temp file.
 struct DownloadManager<'a> {
     pb: &'a ProgressBar,
     temp_file: NamedTempFile,
+    hasher: Sha256,
 }
 
 impl<'a> DownloadManager<'a> {
     /// Creates a new DownloadManager.
     fn new(pb: &'a ProgressBar) -> AppResult<Self> {
         let temp_file = NamedTempFile::new()?;
-        Ok(Self { pb, temp_file })
-    }
-
-    /// Configures the progress bar for a download.
-    fn setup_progress_bar(&self, asset_name: &str, total_size: u64) -> AppResult<()> {
-        let download_style = ProgressStyle::with_template(
-            "{spinner:.green} {msg}\n{wide_bar:.cyan/blue} {bytes}/{total_bytes} ({eta})",
-        )?
-        .progress_chars("#>-");
-
-        self.pb.set_style(download_style);
-        self.pb.set_length(total_size);
-        self.pb
-            .set_message(format!("Downloading {}", style(asset_name).cyan()));
-        Ok(())
+        Ok(Self {
+            pb,
+            temp_file,
+            hasher: Sha256::new(),
+        })
     }
 
     /// Writes a chunk of bytes to the temporary file and updates the progress bar.
     fn write_chunk(&mut self, chunk: &[u8]) -> AppResult<()> {
         self.temp_file.write_all(chunk)?;
+        self.hasher.update(chunk);
         self.pb.inc(chunk.len() as u64);
         Ok(())
     }
 
-    /// Consumes the manager and returns the underlying temporary file.
-    fn finish(self) -> NamedTempFile {
-        self.temp_file
+    /// Consumes the manager and returns the underlying temporary file and its SHA-256 digest.
+    fn finish(self) -> (NamedTempFile, String) {
+        (self.temp_file, hex::encode(self.hasher.finalize()))
+    }
+}
+
+/// A reader that hashes and reports progress for every byte read through it.
+/// It fails once the run is cancelled, so an extraction running on a blocking thread
+/// stops writing instead of outliving the rollback of the tool's files.
+struct HashingReader<R> {
+    inner: R,
+    pb: ProgressBar,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R, pb: ProgressBar) -> Self {
+        Self {
+            inner,
+            pb,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Reads whatever the extractor left unconsumed (e.g. trailing padding, or the rest
+    /// of the archive after a single file was found) so the digest covers the whole asset.
+    fn finish(mut self) -> io::Result<String> {
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(hex::encode(self.hasher.finalize()))
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        if interrupt::token().is_cancelled() {
+            return Err(io::Error::other("Download cancelled"));
+        }
+        self.hasher.update(&buf[..bytes_read]);
+        self.pb.inc(bytes_read as u64);
+        Ok(bytes_read)
+    }
+}
+
+/// A blocking `Read` adapter over the chunks forwarded by the async download loop.
+struct ChannelReader {
+    rx: mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ChannelReader {
+    fn new(rx: mpsc::Receiver<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            rx,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Whether a failed download is worth another attempt. Connection problems, timeouts, cut-off
+/// transfers and server errors are; a missing asset, a full disk or a corrupt archive would
+/// fail the same way again. Nothing is retried once the run is being stopped.
+fn is_retryable(error: &anyhow::Error) -> bool {
+    if interrupt::token().is_cancelled() {
+        return false;
+    }
+    let Some(error) = error.downcast_ref::<reqwest::Error>() else {
+        return false;
+    };
+    match error.status() {
+        Some(status) => {
+            status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        }
+        None => error.is_connect() || error.is_timeout() || error.is_request() || error.is_body(),
     }
 }
 
 /// Downloads a file to a temporary file on disk, showing progress.
+/// Returns the temporary file together with the SHA-256 digest of its contents.
 async fn download_to_temp_file(
     url: &str,
     asset_name: &str,
     pb: &ProgressBar,
     client: &reqwest::Client,
-) -> AppResult<NamedTempFile> {
+) -> AppResult<(NamedTempFile, String)> {
     let retry_strategy = ExponentialBackoff::from_millis(500).map(jitter).take(3);
 
-    let result = Retry::spawn(retry_strategy, || async {
-        pb.set_position(0);
+    let result = RetryIf::spawn(
+        retry_strategy,
+        || async {
+            pb.set_position(0);
 
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?
-            .error_for_status()
-            .map_err(|e| e.to_string())?;
-        let total_size = response.content_length().unwrap_or(0);
+            let response = client.get(url).send().await?.error_for_status()?;
+            let total_size = response.content_length().unwrap_or(0);
 
-        let mut manager = DownloadManager::new(pb).map_err(|e| e.to_string())?;
-        manager
-            .setup_progress_bar(asset_name, total_size)
-            .map_err(|e| e.to_string())?;
+            let mut manager = DownloadManager::new(pb)?;
+            setup_download_progress_bar(pb, asset_name, total_size)?;
 
-        let mut stream = response.bytes_stream();
+            let mut stream = response.bytes_stream();
 
-        while let Some(item) = stream.next().await {
-            let chunk = item.map_err(|e| format!("Failed to read download chunk: {}", e))?;
-            manager.write_chunk(&chunk).map_err(|e| e.to_string())?;
-        }
+            loop {
+                let item = tokio::select! {
+                    item = stream.next() => item,
+                    _ = interrupt::token().cancelled() => return Err(anyhow!("Download cancelled")),
+                };
+                let Some(item) = item else { break };
+                let chunk = item.context("Failed to read download chunk")?;
+                manager.write_chunk(&chunk)?;
+            }
 
-        Ok(manager.finish())
-    })
+            Ok(manager.finish())
+        },
+        is_retryable,
+    )
     .await;
 
-    result.map_err(|e: String| anyhow!(e))
+    interrupt::check()?;
+    result
+}
+
+/// Pipes the HTTP byte stream of a tar-based archive straight into the decoder and
+/// unpacker, without buffering the archive on disk. Returns the SHA-256 digest of the asset.
+async fn stream_and_extract(
+    url: &str,
+    asset_name: &str,
+    archive_type: ArchiveType,
+    target: &ExtractTarget,
+    extracting: &str,
+    pb: &ProgressBar,
+    client: &reqwest::Client,
+) -> AppResult<String> {
+    let retry_strategy = ExponentialBackoff::from_millis(500).map(jitter).take(3);
+
+    let result = RetryIf::spawn(
+        retry_strategy,
+        || async {
+            pb.set_position(0);
+
+            let response = client.get(url).send().await?.error_for_status()?;
+            let total_size = response.content_length().unwrap_or(0);
+            setup_download_progress_bar(pb, asset_name, total_size)?;
+
+            // The decoders and the tar unpacker are synchronous, so they run on a blocking
+            // thread and pull chunks from a bounded channel fed by the download loop below.
+            let (tx, rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+            let target = target.clone();
+            let pb_clone = pb.clone();
+            let extractor = task::spawn_blocking(move || -> AppResult<String> {
+                let mut reader = HashingReader::new(ChannelReader::new(rx), pb_clone);
+                target.extract_stream(&mut reader, archive_type)?;
+                Ok(reader.finish()?)
+            });
+
+            let mut stream = response.bytes_stream();
+            let mut download_error = None;
+            loop {
+                let item = tokio::select! {
+                    item = stream.next() => item,
+                    _ = interrupt::token().cancelled() => {
+                        let _ = tx.send(Err(io::Error::other("Download cancelled"))).await;
+                        download_error = Some(anyhow!("Download cancelled"));
+                        break;
+                    }
+                };
+                let Some(item) = item else { break };
+                match item {
+                    Ok(chunk) => {
+                        if tx.send(Ok(chunk.to_vec())).await.is_err() {
+                            // The extractor bailed out early; its error is reported below.
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let error = anyhow::Error::new(e).context("Failed to read download chunk");
+                        // Fail the extractor too, so it does not mistake a truncated stream for EOF.
+                        let _ = tx.send(Err(io::Error::other(format!("{:#}", error)))).await;
+                        download_error = Some(error);
+                        break;
+                    }
+                }
+            }
+            drop(tx);
+            if download_error.is_none() {
+                // The unpacker may still be working through the last buffered chunks.
+                pb.set_message(extracting.to_string());
+            }
+
+            // Always wait for the extractor before returning, so a retry never races with it.
+            let extracted = extractor.await.context("Extraction task panicked")?;
+            if let Some(error) = download_error {
+                return Err(error);
+            }
+            extracted
+        },
+        is_retryable,
+    )
+    .await;
+
+    interrupt::check()?;
+    result
+}
+
+/// Downloads an archive and unpacks it according to `target`, returning the
+/// SHA-256 digest of the downloaded asset. `extracting` is shown once the download is
+/// done and unpacking is all that is left.
+///
+/// Tar-based archives are streamed straight into the unpacker. Zip archives need
+/// `Seek` to read their central directory, so they still go through a temp file.
+pub async fn download_and_extract(
+    url: &str,
+    asset_name: &str,
+    archive_type: ArchiveType,
+    target: &ExtractTarget,
+    extracting: &str,
+    pb: &ProgressBar,
+    client: &reqwest::Client,
+) -> AppResult<String> {
+    let digest = if archive_type.is_streamable() {
+        tracing::debug!(asset = asset_name, "Streaming archive into extractor");
+        stream_and_extract(
+            url,
+            asset_name,
+            archive_type,
+            target,
+            extracting,
+            pb,
+            client,
+        )
+        .await?
+    } else {
+        tracing::debug!(asset = asset_name, "Downloading archive to a temp file");
+        let (temp_file, digest) = download_to_temp_file(url, asset_name, pb, client).await?;
+        pb.set_message(extracting.to_string());
+        target.extract_seekable(temp_file.reopen()?, archive_type)?;
+        digest
+    };
+
+    tracing::info!(asset = asset_name, sha256 = %digest, "Downloaded and extracted asset");
+    Ok(digest)
+}
+
+/// Describes what to unpack from a downloaded archive, and where to.
+#[derive(Debug, Clone)]
+pub enum ExtractTarget {
+    /// Extract a single file, matched by its file name, into `target_dir`.
+    SingleFile {
+        target_dir: PathBuf,
+        file_name: String,
+    },
+    /// Extract the entire archive into `target_dir`, stripping the top-level directory.
+    Full { target_dir: PathBuf },
+    /// Extract only the contents of `sub_dir_name` into `target_dir`.
+    SubDirectory {
+        target_dir: PathBuf,
+        sub_dir_name: String,
+    },
+}
+
+impl ExtractTarget {
+    /// Unpacks a tar-based archive from a forward-only reader.
+    fn extract_stream<R: Read>(&self, reader: R, archive_type: ArchiveType) -> AppResult<()> {
+        let mut archive = Archive::new(tar_decoder(reader, archive_type)?);
+        match self {
+            ExtractTarget::SingleFile {
+                target_dir,
+                file_name,
+            } => extract_single_file_from_tar(&mut archive, target_dir, file_name),
+            ExtractTarget::Full { target_dir } => unpack_tar_archive(&mut archive, target_dir),
+            ExtractTarget::SubDirectory {
+                target_dir,
+                sub_dir_name,
+            } => {
+                fs::create_dir_all(target_dir)?;
+                unpack_tar_sub_directory(&mut archive, target_dir, &format!("/{}/", sub_dir_name))
+            }
+        }
+    }
+
+    /// Unpacks any supported archive from a seekable reader.
+    fn extract_seekable<R: Read + Seek>(
+        &self,
+        reader: R,
+        archive_type: ArchiveType,
+    ) -> AppResult<()> {
+        match self {
+            ExtractTarget::SingleFile {
+                target_dir,
+                file_name,
+            } => extract_single_file_from_archive(reader, archive_type, target_dir, file_name),
+            ExtractTarget::Full { target_dir } => {
+                extract_full_archive(reader, archive_type, target_dir)
+            }
+            ExtractTarget::SubDirectory {
+                target_dir,
+                sub_dir_name,
+            } => extract_sub_directory(reader, archive_type, target_dir, sub_dir_name),
+        }
+    }
 }
 
 /// Defines how a downloaded archive should be processed.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ExtractionStrategy<'a> {
     /// Extract a single binary file from the archive.
     SingleBinary { binary_name: &'a str },
     /// Extract the entire archive to a specified directory.
     FullArchive {
+        /// The directory, relative to the environment root, to extract the archive into.
+        dir: &'a str,
         /// The path to the binary within the extracted archive, relative to the archive root.
         path_in_archive: &'a str,
     },
@@ -312,20 +1123,24 @@ pub enum ExtractionStrategy<'a> {
 
 /// A unified function to download a tool from a GitHub release and install it
 /// based on the specified extraction strategy.
-#[tracing::instrument(skip(context, pb, spinner_style))]
-pub async fn provision_from_github_release<'a>(
+///
+/// Every matching asset is tried in order of preference: after extraction the
+/// binary is smoke-tested with the tool's probe command, and a build that fails
+/// to run is removed again before falling back to the next candidate.
+#[tracing::instrument(skip(tool, context, pb, spinner_style), fields(tool = tool.name()))]
+pub async fn provision_from_github_release<T: Tool + ?Sized>(
+    tool: &T,
     context: &ProvisionContext,
-    name: &'a str,
-    repo: &'a str,
-    binary_name: &'a str,
-    strategy: ExtractionStrategy<'a>,
+    strategy: ExtractionStrategy<'_>,
     pb: &ProgressBar,
     spinner_style: &ProgressStyle,
-) -> AppResult<()> {
-    // 1. Find the asset URL
-    let (download_url, asset_name) = find_github_release_asset_url(
-        name,
-        repo,
+) -> AppResult<Provenance> {
+    let name = tool.name();
+
+    // 1. Find the candidate asset URLs
+    let candidates = find_github_release_asset_candidates(
+        tool.asset_spec(),
+        tool.repo(),
         "https://api.github.com",
         env::consts::OS,
         env::consts::ARCH,
@@ -333,43 +1148,231 @@ pub async fn provision_from_github_release<'a>(
     )
     .await?;
 
-    // 2. Download to a temp file
-    let temp_file = download_to_temp_file(&download_url, &asset_name, pb, &context.client).await?;
-    let file = temp_file.reopen()?;
-    let archive_type = ArchiveType::from_asset_name(&asset_name)?;
+    let binary_path_in_env = context.env_dir.join("bin").join(tool.binary_name());
+    let mut failures = Vec::new();
+    for (download_url, asset_name) in candidates {
+        // 2. Download and extract based on the strategy
+        let installed = install_release_asset(
+            context,
+            name,
+            tool.binary_name(),
+            strategy,
+            &download_url,
+            &asset_name,
+            pb,
+        )
+        .await;
+        pb.set_style(spinner_style.clone());
+
+        // 3. Adapt the binary to non-FHS hosts, then make sure it actually runs here
+        let verified = match installed {
+            Ok(sha256) => {
+                pb.set_message(format!("Verifying {}...", style(name).bold()));
+                match adapt_to_host_layout(&binary_path_in_env) {
+                    Ok(loader_fix) => verify_binary(&binary_path_in_env, &tool.probe_args())
+                        .await
+                        .map(|()| (sha256, loader_fix)),
+                    Err(e) => Err(format!("{:#}", e)),
+                }
+            }
+            Err(e) => {
+                tracing::warn!(asset = %asset_name, "Failed to install release asset: {:#}", e);
+                Err(format!("{:#}", e))
+            }
+        };
+        match verified {
+            Ok((sha256, loader_fix)) => {
+                pb.set_message(format!("Installed {} successfully", style(name).bold()));
+                let mut provenance = Provenance::download(&download_url, &asset_name, sha256);
+                provenance.loader_fix = loader_fix;
+                return Ok(provenance);
+            }
+            Err(reason) => {
+                tracing::warn!(asset = %asset_name, %reason, "Release asset is not usable");
+                pb.println(format!(
+                    " › {} from {} could not be installed or does not run on this system, trying the next candidate...",
+                    style(name).bold(),
+                    style(&asset_name).cyan()
+                ));
+                remove_release_install(context, strategy, &binary_path_in_env)?;
+                // A stopped run must not go on to the next candidate.
+                interrupt::check()?;
+                failures.push(format!("{}: {}", asset_name, reason));
+            }
+        }
+    }
 
-    pb.set_style(spinner_style.clone());
+    Err(anyhow!(
+        "No release asset of {} could be installed and run on this system:\n  {}",
+        name,
+        failures.join("\n  ")
+    ))
+}
 
-    // 3. Extract based on the strategy
-    match strategy {
+/// Downloads a single release asset and installs it according to `strategy`.
+/// Returns the SHA-256 digest of the asset.
+async fn install_release_asset(
+    context: &ProvisionContext,
+    name: &str,
+    binary_name: &str,
+    strategy: ExtractionStrategy<'_>,
+    download_url: &str,
+    asset_name: &str,
+    pb: &ProgressBar,
+) -> AppResult<String> {
+    let archive_type = ArchiveType::from_asset_name(asset_name)?;
+
+    let sha256 = match strategy {
         ExtractionStrategy::SingleBinary { binary_name } => {
-            pb.set_message(format!("Extracting {}...", style(binary_name).bold()));
             let bin_dir = context.env_dir.join("bin");
-            extract_single_file_from_archive(file, archive_type, &bin_dir, binary_name)?;
+            let target = ExtractTarget::SingleFile {
+                target_dir: bin_dir.clone(),
+                file_name: binary_name.to_string(),
+            };
+            let sha256 = download_and_extract(
+                download_url,
+                asset_name,
+                archive_type,
+                &target,
+                &format!("Extracting {}...", style(binary_name).bold()),
+                pb,
+                &context.client,
+            )
+            .await?;
 
             #[cfg(unix)]
             {
                 let tool_path = bin_dir.join(binary_name);
                 fs::set_permissions(&tool_path, fs::Permissions::from_mode(0o755))?;
             }
+            sha256
         }
-        ExtractionStrategy::FullArchive { path_in_archive } => {
-            pb.set_message(format!("Extracting archive for {}...", style(name).bold()));
-            let tool_dir = context.env_dir.join(name);
+        ExtractionStrategy::FullArchive {
+            dir,
+            path_in_archive,
+        } => {
+            let tool_dir = context.env_dir.join(dir);
             fs::create_dir_all(&tool_dir)?;
 
-            extract_full_archive(file, archive_type, &tool_dir)?;
+            let target = ExtractTarget::Full {
+                target_dir: tool_dir.clone(),
+            };
+            let sha256 = download_and_extract(
+                download_url,
+                asset_name,
+                archive_type,
+                &target,
+                &format!("Extracting archive for {}...", style(name).bold()),
+                pb,
+                &context.client,
+            )
+            .await?;
 
             let binary_path_in_archive = tool_dir.join(path_in_archive);
             let binary_path_in_env = context.env_dir.join("bin").join(binary_name);
             create_symlink(&binary_path_in_archive, &binary_path_in_env)?;
+            sha256
+        }
+    };
+
+    Ok(sha256)
+}
+
+/// On non-FHS hosts (NixOS, Guix), makes a downloaded binary loadable: a static
+/// build needs nothing, while a dynamically linked one gets its interpreter and
+/// RPATH rewritten to the host's loader. Returns the approach taken, if any.
+fn adapt_to_host_layout(binary_path_in_env: &Path) -> AppResult<Option<LoaderFix>> {
+    let HostLayout::NonFhs { loader } = platform::host_layout() else {
+        return Ok(None);
+    };
+
+    let binary = fs::canonicalize(binary_path_in_env)?;
+    let Some(info) = ElfInfo::read(&binary)? else {
+        return Ok(None);
+    };
+    match info.interpreter {
+        None => Ok(Some(LoaderFix::StaticBuild)),
+        Some(interpreter) if Path::new(&interpreter).exists() => Ok(None),
+        Some(interpreter) => {
+            let loader = loader.as_deref().ok_or_else(|| {
+                anyhow!(
+                    "its interpreter {} does not exist and the system's loader could not be found",
+                    interpreter
+                )
+            })?;
+            elf::patch_interpreter(&binary, loader).map(Some)
         }
     }
+}
 
-    pb.set_message(format!("Installed {} successfully", style(name).bold()));
+/// Removes whatever `install_release_asset` put in place, so the next candidate starts clean.
+fn remove_release_install(
+    context: &ProvisionContext,
+    strategy: ExtractionStrategy<'_>,
+    binary_path_in_env: &Path,
+) -> AppResult<()> {
+    if binary_path_in_env.symlink_metadata().is_ok() {
+        fs::remove_file(binary_path_in_env)?;
+    }
+    if let ExtractionStrategy::FullArchive { dir, .. } = strategy {
+        let tool_dir = context.env_dir.join(dir);
+        if tool_dir.exists() {
+            fs::remove_dir_all(&tool_dir)?;
+        }
+    }
     Ok(())
 }
 
+/// Checks a freshly installed binary: its ELF headers must match the host
+/// (machine type, bitness, interpreter, glibc), and its probe command must succeed.
+async fn verify_binary(path: &Path, probe_args: &[&str]) -> Result<(), String> {
+    match ElfInfo::read(path) {
+        Ok(Some(info)) => {
+            tracing::debug!(
+                machine = info.machine_name(),
+                interpreter = ?info.interpreter,
+                needed = ?info.needed,
+                required_glibc = ?info.required_glibc,
+                "Inspected downloaded binary"
+            );
+            info.check_runnable()?;
+        }
+        Ok(None) => {}
+        Err(e) => return Err(format!("{:#}", e)),
+    }
+    probe_binary(path, probe_args).await
+}
+
+/// Runs a freshly installed binary with `args` to check that it starts at all.
+/// On failure, returns a description including the captured stderr.
+async fn probe_binary(path: &Path, args: &[&str]) -> Result<(), String> {
+    let child = AsyncCommand::new(path)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to execute {}: {}", path.display(), e))?;
+
+    let output = tokio::time::timeout(PROBE_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("`{}` timed out", args.join(" ")))?
+        .map_err(|e| format!("failed to wait for {}: {}", path.display(), e))?;
+
+    if output.status.success() {
+        tracing::debug!(path = %path.display(), "Smoke test passed");
+        Ok(())
+    } else {
+        Err(format!(
+            "`{}` exited with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
 #[tracing::instrument(skip(pb, client), fields(name = name, dest_dir = %dest_dir.display()))]
 pub async fn provision_source_share(
     dest_dir: &Path,
@@ -387,14 +1390,22 @@ pub async fn provision_source_share(
     let (source_url, asset_name) =
         find_github_source_tarball_url(repo, "https://api.github.com", client).await?;
 
-    // 2. Download to a temp file
-    let temp_file = download_to_temp_file(&source_url, &asset_name, pb, client).await?;
-    let file = temp_file.reopen()?;
-
-    // 3. Selectively extract the 'share' directory
-    pb.set_message(format!("Extracting 'share' for {}...", style(name).bold()));
+    // 2. Stream the tarball, selectively extracting the 'share' directory.
     // Source tarballs from GitHub are always .tar.gz
-    extract_sub_directory(file, ArchiveType::TarGz, dest_dir, "share")?;
+    let target = ExtractTarget::SubDirectory {
+        target_dir: dest_dir.to_path_buf(),
+        sub_dir_name: "share".to_string(),
+    };
+    download_and_extract(
+        &source_url,
+        &asset_name,
+        ArchiveType::TarGz,
+        &target,
+        &format!("Extracting 'share' for {}...", style(name).bold()),
+        pb,
+        client,
+    )
+    .await?;
 
     Ok(())
 }
@@ -440,6 +1451,19 @@ async fn find_github_source_tarball_url(
     result.map_err(|e: String| anyhow!(e))
 }
 
+/// Identifies the release asset a tool needs, independent of the target platform.
+#[derive(Debug, Clone, Copy)]
+pub struct AssetSpec<'a> {
+    /// The tool name, matched against asset file names.
+    pub name: &'a str,
+    /// The minimum glibc the tool's GNU builds need, if known.
+    pub min_glibc: Option<(u32, u32)>,
+    /// The OS names matched on Linux and Android instead of target triples, if any.
+    pub os_keywords: Option<&'a [&'a str]>,
+    /// The archive format, matched against asset file names except on Windows.
+    pub extension: &'a str,
+}
+
 /// Specifies which GitHub release to target.
 #[derive(Debug)]
 pub enum ReleaseSpecifier<'a> {
@@ -448,21 +1472,22 @@ pub enum ReleaseSpecifier<'a> {
     Tag(&'a str),
 }
 
-/// A generic, asynchronous function to find a release asset URL from the GitHub API.
+/// A generic, asynchronous function to find release asset URLs from the GitHub API,
+/// ordered from most to least preferred.
 /// It can target either the latest release or a release by a specific tag.
 #[tracing::instrument(skip(client), fields(repo = repo, os = os, arch = arch))]
 async fn find_release_asset(
-    name: &str,
+    asset: AssetSpec<'_>,
     repo: &str,
     specifier: ReleaseSpecifier<'_>,
     base_url: &str,
     os: &str,
     arch: &str,
     client: &reqwest::Client,
-) -> AppResult<(String, String)> {
+) -> AppResult<Vec<(String, String)>> {
     let retry_strategy = ExponentialBackoff::from_millis(500).map(jitter).take(3);
 
-    let result: Result<(String, String), String> = Retry::spawn(retry_strategy, || async {
+    let result: Result<Vec<(String, String)>, String> = Retry::spawn(retry_strategy, || async {
         let repo_url = match specifier {
             ReleaseSpecifier::Latest => format!("{}/repos/{}/releases/latest", base_url, repo),
             ReleaseSpecifier::Tag(tag) => format!("{}/repos/{}/releases/tags/{}", base_url, repo, tag),
@@ -485,68 +1510,89 @@ async fn find_release_asset(
             )
         })?;
 
-        find_best_asset_match(name, assets, os, arch)
+        find_asset_candidates(asset, assets, os, arch)
     })
     .await;
 
     result.map_err(|e| anyhow!(e))
 }
 
-/// The core asset-matching logic, extracted into a synchronous function
-/// so it can be shared by both async and blocking API callers.
+/// Returns the most preferred asset matching the platform.
 fn find_best_asset_match(
-    name: &str,
+    asset: AssetSpec<'_>,
     assets: &[Value],
     os: &str,
     arch: &str,
 ) -> Result<(String, String), String> {
+    find_asset_candidates(asset, assets, os, arch).map(|mut candidates| candidates.remove(0))
+}
+
+/// The core asset-matching logic, extracted into a synchronous function
+/// so it can be shared by both async and blocking API callers.
+/// Returns every matching asset, at most one per OS target, in order of preference.
+fn find_asset_candidates(
+    asset: AssetSpec<'_>,
+    assets: &[Value],
+    os: &str,
+    arch: &str,
+) -> Result<Vec<(String, String)>, String> {
     tracing::debug!(asset_count = assets.len(), "Found release assets");
+    let name = asset.name;
 
     let os_targets: Vec<&str> = match os {
         "linux" => {
-            let mut gnu_preferred = true;
-
-            #[cfg(target_os = "linux")]
-            {
-                // Atuin's GNU binary is built against glibc 2.35.
-                // If the system's glibc is older, we prefer musl.
-                const MIN_GLIBC_VERSION: (u32, u32) = (2, 35);
-
-                if let Some((major, minor)) = get_glibc_version() {
-                    if (major, minor) < MIN_GLIBC_VERSION {
+            // The GNU build is only ruled out when the tool declares a glibc
+            // requirement that the system's glibc does not meet.
+            let gnu_preferred = match (platform::host_libc(), asset.min_glibc) {
+                // Prebuilt GNU binaries hardcode an FHS loader path, so static
+                // musl builds are the safe choice on hosts like NixOS and Guix.
+                _ if *platform::host_layout() != HostLayout::Fhs => {
+                    tracing::info!("Non-FHS system detected. Prioritizing musl build.");
+                    false
+                }
+                (Libc::Glibc { .. }, None) => true,
+                (
+                    libc @ Libc::Glibc {
+                        version: Some((major, minor)),
+                    },
+                    Some(required),
+                ) => {
+                    let supported = libc.supports_glibc(required);
+                    if !supported {
                         tracing::info!(
-                            "System glibc version {}.{} is older than required {}.{}. Prioritizing musl build.",
+                            "System glibc version {}.{} is older than the {}.{} required by {}. Prioritizing musl build.",
                             major,
                             minor,
-                            MIN_GLIBC_VERSION.0,
-                            MIN_GLIBC_VERSION.1
+                            required.0,
+                            required.1,
+                            name
                         );
-                        gnu_preferred = false;
                     }
-                } else {
+                    supported
+                }
+                (Libc::Glibc { version: None }, Some(_)) => {
                     tracing::warn!(
                         "Could not determine glibc version. Defaulting to musl for safety."
                     );
-                    gnu_preferred = false; // Default to safer musl if check fails
+                    false
+                }
+                (libc, _) => {
+                    tracing::info!(?libc, "System does not use glibc. Prioritizing musl build.");
+                    false
                 }
-            }
-
-            let default_targets = if gnu_preferred {
-                vec!["unknown-linux-gnu", "unknown-linux-musl"]
-            } else {
-                vec!["unknown-linux-musl", "unknown-linux-gnu"]
             };
 
-            match name {
-                "fish" | "helix" => vec!["linux"],
-                _ => default_targets,
+            match asset.os_keywords {
+                Some(keywords) => keywords.to_vec(),
+                None if gnu_preferred => vec!["unknown-linux-gnu", "unknown-linux-musl"],
+                None => vec!["unknown-linux-musl", "unknown-linux-gnu"],
             }
         }
         "android" => {
             // Android does not use glibc, so musl is generally the better choice if available.
-            match name {
-                "fish" | "helix" => vec!["linux"],
-                _ => vec!["unknown-linux-musl", "unknown-linux-gnu"],
+            match asset.os_keywords {
+                Some(keywords) => keywords.to_vec(),
+                None => vec!["unknown-linux-musl", "unknown-linux-gnu"],
             }
         }
         "macos" => vec!["apple-darwin"],
@@ -557,19 +1603,15 @@ fn find_best_asset_match(
     let ext = if os == "windows" {
         "zip"
     } else {
-        match name {
-            "helix" if os == "linux" || os == "android" => "tar.xz",
-            "helix" if os == "macos" => "zip",
-            "fish" => "tar.xz",
-            _ => "tar.gz",
-        }
+        asset.extension
     };
 
+    let mut candidates: Vec<(String, String)> = Vec::new();
     for os_target in &os_targets {
         // For Helix, the tag is part of the asset name, but `name` is "helix-editor/helix".
         // We only want to match against "helix".
         let name_to_match = if name.contains('/') {
-            name.split('/').last().unwrap_or(name)
+            name.split('/').next_back().unwrap_or(name)
         } else {
             name
         };
@@ -577,39 +1619,44 @@ fn find_best_asset_match(
         let fragments_to_use = vec![name_to_match, arch, *os_target, ext];
         tracing::debug!(fragments = ?fragments_to_use, "Searching for asset");
 
-        for asset in assets {
-            let asset_name = asset["name"].as_str().unwrap_or("");
+        for release_asset in assets {
+            let asset_name = release_asset["name"].as_str().unwrap_or("");
             let lower_name = asset_name.to_lowercase();
 
             if fragments_to_use
                 .iter()
                 .all(|frag| lower_name.contains(&frag.to_lowercase()))
+                && let Some(url) = release_asset["browser_download_url"].as_str()
             {
-                if let Some(url) = asset["browser_download_url"].as_str() {
+                if !candidates.iter().any(|(known, _)| known == url) {
                     tracing::info!(asset = asset_name, "Found matching release asset");
-                    return Ok((url.to_string(), asset_name.to_string()));
+                    candidates.push((url.to_string(), asset_name.to_string()));
                 }
+                break;
             }
         }
     }
 
-    Err(format!(
-        "Could not find a matching release asset for {} on {} {}",
-        name, os, arch
-    ))
+    if candidates.is_empty() {
+        return Err(format!(
+            "Could not find a matching release asset for {} on {} {}",
+            name, os, arch
+        ));
+    }
+    Ok(candidates)
 }
 
 #[tracing::instrument(skip(client), fields(repo = repo, os = os, arch = arch))]
-async fn find_github_release_asset_url(
-    name: &str,
+async fn find_github_release_asset_candidates(
+    asset: AssetSpec<'_>,
     repo: &str,
     base_url: &str,
     os: &str,
     arch: &str,
     client: &reqwest::Client,
-) -> AppResult<(String, String)> {
+) -> AppResult<Vec<(String, String)>> {
     find_release_asset(
-        name,
+        asset,
         repo,
         ReleaseSpecifier::Latest,
         base_url,
@@ -620,7 +1667,7 @@ async fn find_github_release_asset_url(
     .await
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ArchiveType {
     TarGz,
     TarXz,
@@ -640,6 +1687,44 @@ impl ArchiveType {
             Err(anyhow!("Unsupported archive format for {}", name))
         }
     }
+
+    /// Whether the archive can be unpacked from a forward-only stream.
+    /// Zip archives keep their central directory at the end of the file and need `Seek`.
+    pub fn is_streamable(&self) -> bool {
+        !matches!(self, ArchiveType::Zip)
+    }
+}
+
+/// Wraps the reader of a tar-based archive in the matching decompressor.
+fn tar_decoder<'a, R: Read + 'a>(
+    reader: R,
+    archive_type: ArchiveType,
+) -> AppResult<Box<dyn Read + 'a>> {
+    match archive_type {
+        ArchiveType::TarGz => Ok(Box::new(GzDecoder::new(reader))),
+        ArchiveType::TarXz => Ok(Box::new(XzDecoder::new(reader))),
+        ArchiveType::Zip => Err(anyhow!("Zip archives cannot be read as a tar stream")),
+    }
+}
+
+/// Extracts the first entry whose file name matches `file_name` from a tar archive.
+fn extract_single_file_from_tar<R: Read>(
+    archive: &mut Archive<R>,
+    target_dir: &Path,
+    file_name: &str,
+) -> AppResult<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.file_name().is_some_and(|n| n == file_name) {
+            entry.unpack(target_dir.join(file_name))?;
+            return Ok(());
+        }
+    }
+
+    Err(anyhow!(
+        "Could not find '{}' in the downloaded archive.",
+        file_name
+    ))
 }
 
 /// A generic function to extract a single file from a `.tar.gz`, `.tar.xz`, or `.zip` archive.
@@ -652,48 +1737,22 @@ fn extract_single_file_from_archive<R: Read + Seek>(
 ) -> AppResult<()> {
     let target_path = target_dir.join(binary_name);
     match archive_type {
-        ArchiveType::TarGz => {
-            let tar = GzDecoder::new(reader);
-            let mut archive = Archive::new(tar);
-            for entry in archive.entries()? {
-                let mut entry = entry?;
-                if entry
-                    .path()?
-                    .file_name()
-                    .map_or(false, |n| n == binary_name)
-                {
-                    entry.unpack(&target_path)?;
-                    return Ok(());
-                }
-            }
-        }
-        ArchiveType::TarXz => {
-            let tar = XzDecoder::new(reader);
-            let mut archive = Archive::new(tar);
-            for entry in archive.entries()? {
-                let mut entry = entry?;
-                if entry
-                    .path()?
-                    .file_name()
-                    .map_or(false, |n| n == binary_name)
-                {
-                    entry.unpack(&target_path)?;
-                    return Ok(());
-                }
-            }
+        ArchiveType::TarGz | ArchiveType::TarXz => {
+            let mut archive = Archive::new(tar_decoder(reader, archive_type)?);
+            return extract_single_file_from_tar(&mut archive, target_dir, binary_name);
         }
         ArchiveType::Zip => {
             // ZipArchive::new requires the reader to be mutable
             let mut archive = ZipArchive::new(&mut reader)?;
             for i in 0..archive.len() {
                 let mut file = archive.by_index(i)?;
-                if let Some(path) = file.enclosed_name() {
-                    if path.file_name().map_or(false, |n| n == binary_name) {
-                        let mut outfile = File::create(&target_path)?;
-                        io::copy(&mut file, &mut outfile)?;
-                        // The `download_and_install_binary` function sets permissions afterwards
-                        return Ok(());
-                    }
+                if let Some(path) = file.enclosed_name()
+                    && path.file_name().is_some_and(|n| n == binary_name)
+                {
+                    let mut outfile = File::create(&target_path)?;
+                    io::copy(&mut file, &mut outfile)?;
+                    // The `download_and_install_binary` function sets permissions afterwards
+                    return Ok(());
                 }
             }
         }
@@ -723,10 +1782,10 @@ fn unpack_tar_archive<R: io::Read>(archive: &mut Archive<R>, target_dir: &Path)
         if entry.header().entry_type().is_dir() {
             fs::create_dir_all(&outpath)?;
         } else {
-            if let Some(p) = outpath.parent() {
-                if !p.exists() {
-                    fs::create_dir_all(p)?;
-                }
+            if let Some(p) = outpath.parent()
+                && !p.exists()
+            {
+                fs::create_dir_all(p)?;
             }
             entry.unpack(&outpath)?;
         }
@@ -742,19 +1801,14 @@ pub fn extract_full_archive<R: Read + Seek>(
     target_dir: &Path,
 ) -> AppResult<()> {
     match archive_type {
-        ArchiveType::TarGz => {
-            let tar = GzDecoder::new(reader);
-            let mut archive = Archive::new(tar);
-            unpack_tar_archive(&mut archive, target_dir)?;
-        }
-        ArchiveType::TarXz => {
-            let tar = XzDecoder::new(reader);
-            let mut archive = Archive::new(tar);
+        ArchiveType::TarGz | ArchiveType::TarXz => {
+            let mut archive = Archive::new(tar_decoder(reader, archive_type)?);
             unpack_tar_archive(&mut archive, target_dir)?;
         }
         ArchiveType::Zip => {
             let mut archive = ZipArchive::new(&mut reader)?;
             for i in 0..archive.len() {
+                interrupt::check()?;
                 let mut file = archive.by_index(i)?;
                 if let Some(enclosed_name) = file.enclosed_name() {
                     tracing::trace!(entry_path = ?enclosed_name, "Unpacking archive entry");
@@ -765,10 +1819,10 @@ pub fn extract_full_archive<R: Read + Seek>(
                     if file.name().ends_with('/') {
                         fs::create_dir_all(&outpath)?;
                     } else {
-                        if let Some(p) = outpath.parent() {
-                            if !p.exists() {
-                                fs::create_dir_all(p)?;
-                            }
+                        if let Some(p) = outpath.parent()
+                            && !p.exists()
+                        {
+                            fs::create_dir_all(p)?;
                         }
                         let mut outfile = fs::File::create(&outpath)?;
                         io::copy(&mut file, &mut outfile)?;
@@ -822,25 +1876,33 @@ pub fn provision_helix_runtime_for_symlink(
     tracing::debug!(version = %version_tag, "Parsed helix version from symlinked binary");
 
     // 2. Find the GitHub release asset URL for that specific tag.
+    // Only the runtime is extracted from this asset, so glibc compatibility is irrelevant.
+    let asset = AssetSpec {
+        min_glibc: None,
+        ..helix::Helix.asset_spec()
+    };
     let (download_url, asset_name) = find_github_release_asset_url_by_tag(
-        "helix-editor/helix",
+        asset,
+        helix::Helix.repo(),
         &version_tag,
         env::consts::OS,
         env::consts::ARCH,
         "https://api.github.com",
     )?;
 
-    // 3. Download the archive to a temp file.
-    let temp_file = download_to_temp_file_blocking(&download_url, &asset_name, pb)?;
-
-    // 4. Selectively extract ONLY the `runtime` directory.
+    // 3. Download the archive, selectively extracting ONLY the `runtime` directory.
+    // This runs on a blocking thread that cancellation cannot drop, so it checks
+    // for cancellation itself (see `HashingReader`).
+    interrupt::check()?;
     let helix_dir = env_dir.join("helix");
     fs::create_dir_all(&helix_dir)?;
     tracing::debug!(path = %helix_dir.display(), "Ensured helix directory exists");
 
-    let file = temp_file.reopen()?;
-    let archive_type = ArchiveType::from_asset_name(&asset_name)?;
-    extract_sub_directory(file, archive_type, &helix_dir, "runtime")?;
+    let target = ExtractTarget::SubDirectory {
+        target_dir: helix_dir,
+        sub_dir_name: "runtime".to_string(),
+    };
+    download_and_extract_blocking(&download_url, &asset_name, &target, pb)?;
 
     tracing::info!("Successfully provisioned local Helix runtime.");
     Ok(())
@@ -876,12 +1938,15 @@ fn parse_helix_version_tag(version_output: &str) -> AppResult<String> {
     Ok(caps.get(1).unwrap().as_str().to_string())
 }
 
-/// Downloads a file in a blocking context.
-fn download_to_temp_file_blocking(
+/// Downloads and extracts an archive in a blocking context, returning the SHA-256
+/// digest of the asset. Like `download_and_extract`, only zip archives touch a temp file.
+fn download_and_extract_blocking(
     url: &str,
     asset_name: &str,
+    target: &ExtractTarget,
     pb: &ProgressBar,
-) -> AppResult<NamedTempFile> {
+) -> AppResult<String> {
+    let archive_type = ArchiveType::from_asset_name(asset_name)?;
     pb.set_position(0);
 
     let mut response = reqwest::blocking::Client::builder()
@@ -892,25 +1957,37 @@ fn download_to_temp_file_blocking(
         .error_for_status()?;
 
     let total_size = response.content_length().unwrap_or(0);
+    setup_download_progress_bar(pb, asset_name, total_size)?;
 
-    let mut manager = DownloadManager::new(pb)?;
-    manager.setup_progress_bar(asset_name, total_size)?;
-
-    let mut buffer = [0; 8192]; // 8KB buffer
-    loop {
-        let bytes_read = response.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+    let digest = if archive_type.is_streamable() {
+        let mut reader = HashingReader::new(response, pb.clone());
+        target.extract_stream(&mut reader, archive_type)?;
+        reader.finish()?
+    } else {
+        let mut manager = DownloadManager::new(pb)?;
+        let mut buffer = [0; 8192]; // 8KB buffer
+        loop {
+            let bytes_read = response.read(&mut buffer)?;
+            interrupt::check()?;
+            if bytes_read == 0 {
+                break;
+            }
+            manager.write_chunk(&buffer[..bytes_read])?;
         }
-        manager.write_chunk(&buffer[..bytes_read])?;
-    }
+        let (temp_file, digest) = manager.finish();
+        interrupt::check()?;
+        target.extract_seekable(temp_file.reopen()?, archive_type)?;
+        digest
+    };
 
-    Ok(manager.finish())
+    tracing::info!(asset = asset_name, sha256 = %digest, "Downloaded and extracted asset");
+    Ok(digest)
 }
 
 /// Finds a GitHub release asset URL for a specific version tag.
-#[tracing::instrument(fields(repo = repo, tag = tag, os = os, arch = arch))]
+#[tracing::instrument(skip(asset), fields(repo = repo, tag = tag, os = os, arch = arch))]
 fn find_github_release_asset_url_by_tag(
+    asset: AssetSpec<'_>,
     repo: &str,
     tag: &str,
     os: &str,
@@ -934,10 +2011,7 @@ fn find_github_release_asset_url_by_tag(
         )
     })?;
 
-    // The name of the tool is the first part of the repo string (e.g., "helix-editor/helix" -> "helix")
-    let name = repo.split('/').last().unwrap_or(repo);
-
-    find_best_asset_match(name, assets, os, arch).map_err(anyhow::Error::msg)
+    find_best_asset_match(asset, assets, os, arch).map_err(anyhow::Error::msg)
 }
 
 /// Selectively extracts a subdirectory (e.g., "runtime", "share") from an archive.
@@ -952,47 +2026,40 @@ pub fn extract_sub_directory<R: Read + Seek>(
     let sub_dir_pattern = format!("/{}/", sub_dir_name);
 
     match archive_type {
-        ArchiveType::TarGz => {
-            let tar = GzDecoder::new(reader);
-            let mut archive = Archive::new(tar);
-            unpack_tar_sub_directory(&mut archive, target_dir, &sub_dir_pattern)?;
-        }
-        ArchiveType::TarXz => {
-            let tar = XzDecoder::new(reader);
-            let mut archive = Archive::new(tar);
+        ArchiveType::TarGz | ArchiveType::TarXz => {
+            let mut archive = Archive::new(tar_decoder(reader, archive_type)?);
             unpack_tar_sub_directory(&mut archive, target_dir, &sub_dir_pattern)?;
         }
         ArchiveType::Zip => {
             let mut archive = ZipArchive::new(&mut reader)?;
             for i in 0..archive.len() {
+                interrupt::check()?;
                 let mut file = archive.by_index(i)?;
-                if let Some(enclosed_name) = file.enclosed_name() {
-                    if let Some(sub_dir_index) = enclosed_name
+                if let Some(enclosed_name) = file.enclosed_name()
+                    && let Some(sub_dir_index) = enclosed_name
                         .to_str()
                         .and_then(|s| s.find(&sub_dir_pattern))
-                    {
-                        // Get the path relative to the inside of the sub_dir.
-                        // e.g., for "themes/catppuccin.toml" inside "runtime", this is what we get.
-                        let relative_path_str =
-                            &enclosed_name.to_str().unwrap()[sub_dir_index + 1..];
-                        let relative_path = Path::new(relative_path_str);
-                        let outpath = target_dir.join(relative_path);
-
-                        if file.name().ends_with('/') {
-                            fs::create_dir_all(&outpath)?;
-                        } else {
-                            if let Some(p) = outpath.parent() {
-                                if !p.exists() {
-                                    fs::create_dir_all(p)?;
-                                }
-                            }
-                            let mut outfile = fs::File::create(&outpath)?;
-                            io::copy(&mut file, &mut outfile)?;
-                        }
-                        #[cfg(unix)]
-                        if let Some(mode) = file.unix_mode() {
-                            fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
+                {
+                    // Get the path relative to the inside of the sub_dir.
+                    // e.g., for "themes/catppuccin.toml" inside "runtime", this is what we get.
+                    let relative_path_str = &enclosed_name.to_str().unwrap()[sub_dir_index + 1..];
+                    let relative_path = Path::new(relative_path_str);
+                    let outpath = target_dir.join(relative_path);
+
+                    if file.name().ends_with('/') {
+                        fs::create_dir_all(&outpath)?;
+                    } else {
+                        if let Some(p) = outpath.parent()
+                            && !p.exists()
+                        {
+                            fs::create_dir_all(p)?;
                         }
+                        let mut outfile = fs::File::create(&outpath)?;
+                        io::copy(&mut file, &mut outfile)?;
+                    }
+                    #[cfg(unix)]
+                    if let Some(mode) = file.unix_mode() {
+                        fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
                     }
                 }
             }
@@ -1019,10 +2086,10 @@ fn unpack_tar_sub_directory<R: io::Read>(
 
             let outpath = target_dir.join(relative_path);
 
-            if let Some(p) = outpath.parent() {
-                if !p.exists() {
-                    fs::create_dir_all(p)?;
-                }
+            if let Some(p) = outpath.parent()
+                && !p.exists()
+            {
+                fs::create_dir_all(p)?;
             }
             entry.unpack(&outpath)?;
         }
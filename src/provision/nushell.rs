@@ -0,0 +1,33 @@
+use super::{AssetSpec, Tool};
+use std::env;
+
+pub struct Nushell;
+
+impl Tool for Nushell {
+    fn name(&self) -> &'static str {
+        "nushell"
+    }
+
+    fn repo(&self) -> &'static str {
+        "nushell/nushell"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        "nu"
+    }
+
+    fn min_version(&self) -> Option<&'static str> {
+        // The tools' init scripts are loaded from the vendor autoload dir, added in 0.101.
+        Some("0.101")
+    }
+
+    fn asset_spec(&self) -> AssetSpec<'_> {
+        // Release assets are named like `nu-0.101.0-x86_64-unknown-linux-gnu.tar.gz`.
+        AssetSpec {
+            name: "nu",
+            min_glibc: self.min_glibc(),
+            os_keywords: self.asset_os_keywords(),
+            extension: self.asset_extension(env::consts::OS),
+        }
+    }
+}
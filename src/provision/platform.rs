@@ -0,0 +1,193 @@
+use super::policy;
+use regex::Regex;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// The C library that the host's dynamically linked binaries run against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+    /// GNU libc, with its (major, minor) version when it could be determined.
+    Glibc {
+        version: Option<(u32, u32)>,
+    },
+    Musl,
+    /// Android's libc, e.g. under Termux.
+    Bionic,
+    Unknown,
+}
+
+impl Libc {
+    /// Whether a binary linked against glibc `required` can be expected to run here.
+    pub fn supports_glibc(&self, required: (u32, u32)) -> bool {
+        matches!(self, Libc::Glibc { version: Some(v) } if *v >= required)
+    }
+}
+
+impl fmt::Display for Libc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Libc::Glibc {
+                version: Some((major, minor)),
+            } => write!(f, "glibc {}.{}", major, minor),
+            Libc::Glibc { version: None } => write!(f, "glibc (unknown version)"),
+            Libc::Musl => write!(f, "musl"),
+            Libc::Bionic => write!(f, "bionic"),
+            Libc::Unknown => write!(f, "unknown libc"),
+        }
+    }
+}
+
+/// Directories that hold the dynamic loader on FHS-style systems.
+const LOADER_DIRS: &[&str] = &["/lib", "/lib64", "/usr/lib", "/usr/lib64"];
+
+/// Returns the host's libc. Detection runs once and is shared by all callers.
+pub fn host_libc() -> Libc {
+    static HOST_LIBC: OnceLock<Libc> = OnceLock::new();
+    *HOST_LIBC.get_or_init(|| {
+        let libc = detect_libc();
+        tracing::debug!(?libc, "Detected host libc");
+        libc
+    })
+}
+
+fn detect_libc() -> Libc {
+    if cfg!(target_os = "android") || Path::new("/system/bin/linker64").exists() {
+        return Libc::Bionic;
+    }
+    if !cfg!(target_os = "linux") {
+        return Libc::Unknown;
+    }
+
+    // Prefer the loader this process was actually started with, then fall back to
+    // looking for loaders on disk (e.g. when isoterm itself is a static binary).
+    let loader = loader_from_proc_maps().or_else(find_loader_on_disk);
+    match loader {
+        Some(loader) if is_musl_loader(&loader) => Libc::Musl,
+        Some(loader) => Libc::Glibc {
+            version: glibc_version(&loader),
+        },
+        None => Libc::Unknown,
+    }
+}
+
+/// Finds the dynamic loader mapped into the current process, if any.
+fn loader_from_proc_maps() -> Option<PathBuf> {
+    let maps = fs::read_to_string("/proc/self/maps").ok()?;
+    maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .map(PathBuf::from)
+        .find(|path| is_loader_name(path))
+}
+
+/// Scans the usual loader directories for a glibc or musl loader.
+/// A real glibc loader wins over musl, since musl can be installed alongside glibc
+/// (e.g. Debian's `musl` package) while the rest of the system still uses glibc.
+fn find_loader_on_disk() -> Option<PathBuf> {
+    let mut musl_loader = None;
+    for dir in LOADER_DIRS {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !is_loader_name(&path) {
+                continue;
+            }
+            if is_musl_loader(&path) {
+                musl_loader.get_or_insert(path);
+            } else {
+                return Some(path);
+            }
+        }
+    }
+    musl_loader
+}
+
+fn is_loader_name(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with("ld-linux") || n.starts_with("ld-musl-"))
+}
+
+/// Whether `path` is musl's loader. Alpine's `gcompat` installs `ld-linux-*` as a symlink
+/// to `ld-musl-*`, so the loader is classified by what it resolves to, not by its name.
+fn is_musl_loader(path: &Path) -> bool {
+    let has_musl_name = |path: &Path| {
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("ld-musl-"))
+    };
+    has_musl_name(path) || fs::canonicalize(path).is_ok_and(|target| has_musl_name(&target))
+}
+
+/// Determines the glibc version, asking the linked libc directly when possible and
+/// otherwise running the loader itself (`ld.so --version`), which needs no `ldd`.
+fn glibc_version(loader: &Path) -> Option<(u32, u32)> {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        // SAFETY: `gnu_get_libc_version` returns a pointer to a static, NUL-terminated string.
+        let version = unsafe { std::ffi::CStr::from_ptr(libc::gnu_get_libc_version()) };
+        if let Some(version) = version.to_str().ok().and_then(policy::parse_major_minor) {
+            return Some(version);
+        }
+    }
+
+    let output = Command::new(loader).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // e.g. "ld.so (Debian GLIBC 2.36-9+deb12u4) stable release version 2.36."
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout.lines().next()?;
+    let re = Regex::new(r"version (\d+\.\d+)").ok()?;
+    let caps = re.captures(first_line)?;
+    policy::parse_major_minor(caps.get(1)?.as_str())
+}
+
+/// Whether the host follows the Filesystem Hierarchy Standard closely enough for
+/// prebuilt GNU binaries, which hardcode the loader path, to start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostLayout {
+    Fhs,
+    /// e.g. NixOS or Guix, where the loader lives in a store path.
+    NonFhs {
+        /// The loader used by the host's own binaries, if it could be found.
+        loader: Option<PathBuf>,
+    },
+}
+
+/// Returns the host's filesystem layout. Detection runs once and is shared by all callers.
+pub fn host_layout() -> &'static HostLayout {
+    static HOST_LAYOUT: OnceLock<HostLayout> = OnceLock::new();
+    HOST_LAYOUT.get_or_init(|| {
+        let layout = detect_layout();
+        tracing::debug!(?layout, "Detected host filesystem layout");
+        layout
+    })
+}
+
+fn detect_layout() -> HostLayout {
+    if !cfg!(target_os = "linux") {
+        return HostLayout::Fhs;
+    }
+    let is_nixos = Path::new("/etc/NIXOS").exists();
+    let is_guix = Path::new("/gnu/store").exists() && find_loader_on_disk().is_none();
+    if !is_nixos && !is_guix {
+        return HostLayout::Fhs;
+    }
+    HostLayout::NonFhs {
+        loader: loader_of_system_shell(),
+    }
+}
+
+/// Reads `PT_INTERP` from the host's own `sh`, which on NixOS and Guix points into the store.
+fn loader_of_system_shell() -> Option<PathBuf> {
+    let shell = which::which("sh").ok()?;
+    let shell = fs::canonicalize(shell).ok()?;
+    let info = super::elf::ElfInfo::read(&shell).ok()??;
+    let loader = PathBuf::from(info.interpreter?);
+    loader.exists().then_some(loader)
+}
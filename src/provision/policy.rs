@@ -0,0 +1,93 @@
+use clap::ValueEnum;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Decides whether a tool is taken from the system, downloaded, or either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SourcePolicy {
+    /// Symlink a usable system binary if there is one, otherwise download.
+    #[default]
+    PreferSystem,
+    /// Download, falling back to a usable system binary if the download fails.
+    PreferDownload,
+    /// Only ever symlink a system binary.
+    SystemOnly,
+    /// Never look at the system, always download.
+    DownloadOnly,
+}
+
+impl SourcePolicy {
+    /// Whether system binaries are considered at all under this policy.
+    pub fn allows_system(&self) -> bool {
+        !matches!(self, SourcePolicy::DownloadOnly)
+    }
+}
+
+/// The global and per-tool source policies and version constraints for a run.
+#[derive(Debug, Clone, Default)]
+pub struct SourcePolicies {
+    pub default: SourcePolicy,
+    /// Per-tool overrides of `default`, keyed by tool name.
+    pub per_tool: HashMap<String, SourcePolicy>,
+    /// Per-tool minimum versions, overriding the tools' built-in ones.
+    pub min_versions: HashMap<String, String>,
+    /// Copy system binaries into the environment instead of symlinking them.
+    pub vendor_system: bool,
+}
+
+impl SourcePolicies {
+    pub fn policy_for(&self, tool_name: &str) -> SourcePolicy {
+        self.per_tool
+            .get(tool_name)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// The minimum version for a tool, preferring a user override over `builtin`.
+    pub fn min_version_for<'a>(
+        &'a self,
+        tool_name: &str,
+        builtin: Option<&'a str>,
+    ) -> Option<&'a str> {
+        self.min_versions
+            .get(tool_name)
+            .map(String::as_str)
+            .or(builtin)
+    }
+}
+
+/// Extracts the first dotted version number (e.g. "3.7.1") from `--version` output.
+pub fn parse_version(output: &str) -> Option<Vec<u32>> {
+    static VERSION_RE: OnceLock<Regex> = OnceLock::new();
+    let re = VERSION_RE.get_or_init(|| Regex::new(r"\d+(?:\.\d+)+").unwrap());
+    let version = re.find(output)?;
+    version
+        .as_str()
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Extracts the major and minor components of the first dotted version in `text`,
+/// e.g. (2, 36) from "2.36" or "GLIBC 2.36-9".
+pub fn parse_major_minor(text: &str) -> Option<(u32, u32)> {
+    let version = parse_version(text)?;
+    // `parse_version` only matches versions with at least two components.
+    Some((version[0], version[1]))
+}
+
+/// Compares two dotted versions numerically, treating missing components as zero,
+/// so "25.01" equals "25.1.0".
+pub fn compare_versions(a: &[u32], b: &[u32]) -> Ordering {
+    let len = a.len().max(b.len());
+    (0..len)
+        .map(|i| {
+            let x = a.get(i).copied().unwrap_or(0);
+            let y = b.get(i).copied().unwrap_or(0);
+            x.cmp(&y)
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
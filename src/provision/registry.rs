@@ -0,0 +1,105 @@
+use super::{
+    Tool, atuin::Atuin, declared::DeclaredTool, fish::Fish, helix::Helix, nushell::Nushell,
+    ripgrep::Ripgrep, starship::Starship, zoxide::Zoxide, zsh::Zsh,
+};
+use crate::error::AppResult;
+use anyhow::anyhow;
+use std::sync::Arc;
+
+/// The tools isoterm can provision, in provisioning order.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    /// A registry holding the built-in tools.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::default();
+        let builtins: [Arc<dyn Tool>; 8] = [
+            Arc::new(Fish),
+            Arc::new(Zsh),
+            Arc::new(Nushell),
+            Arc::new(Starship),
+            Arc::new(Zoxide),
+            Arc::new(Atuin),
+            Arc::new(Ripgrep),
+            Arc::new(Helix),
+        ];
+        for tool in builtins {
+            registry
+                .register(tool)
+                .expect("built-in tool names are unique");
+        }
+        registry
+    }
+
+    /// Adds a tool after the ones already registered. Names must be unique.
+    pub fn register(&mut self, tool: Arc<dyn Tool>) -> AppResult<()> {
+        if self.get(tool.name()).is_some() {
+            return Err(anyhow!(
+                "A tool named '{}' is already registered",
+                tool.name()
+            ));
+        }
+        tracing::debug!(tool = tool.name(), "Registered tool");
+        self.tools.push(tool);
+        Ok(())
+    }
+
+    /// Registers the tools declared in a tools file.
+    pub fn register_declared(&mut self, tools: Vec<DeclaredTool>) -> AppResult<()> {
+        for tool in tools {
+            self.register(Arc::new(tool))?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
+        self.tools.iter().find(|tool| tool.name() == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Tool>> {
+        self.tools.iter()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    /// Rejects tool names that are not registered.
+    pub fn check(&self, name: &str) -> AppResult<()> {
+        if self.get(name).is_some() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Unknown tool '{}'. Expected one of: {}",
+                name,
+                self.names().join(", ")
+            ))
+        }
+    }
+
+    /// Resolves `only`, `skip` and `with` lists into the tools to provision, in registry order.
+    /// An empty `only` selects every tool; `with` adds tools back after `skip`.
+    pub fn select(
+        &self,
+        only: &[String],
+        skip: &[String],
+        with: &[String],
+    ) -> AppResult<Vec<Arc<dyn Tool>>> {
+        for name in only.iter().chain(skip).chain(with) {
+            self.check(name)?;
+        }
+        let listed = |list: &[String], tool: &Arc<dyn Tool>| list.iter().any(|n| n == tool.name());
+
+        Ok(self
+            .iter()
+            .filter(|tool| {
+                let selected = (only.is_empty() || listed(only, tool)) && !listed(skip, tool);
+                selected || listed(with, tool)
+            })
+            .cloned()
+            .collect())
+    }
+}
@@ -1,7 +1,16 @@
-use super::Tool;
+use super::{ProvisionContext, Tool};
+use crate::{
+    error::AppResult,
+    shell::{Shell, nu_autoload},
+};
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use std::fs;
+use tokio::process::Command;
 
 pub struct Starship;
 
+#[async_trait]
 impl Tool for Starship {
     fn name(&self) -> &'static str {
         "starship"
@@ -14,4 +23,46 @@ impl Tool for Starship {
     fn binary_name(&self) -> &'static str {
         "starship"
     }
+
+    fn shell_init(&self, shell: Shell) -> Option<String> {
+        let init = match shell {
+            Shell::Fish => "starship init fish | source".to_string(),
+            Shell::Zsh => "eval \"$(starship init zsh)\"".to_string(),
+            Shell::Nushell => nu_autoload("starship", "starship init nu"),
+        };
+        Some(init)
+    }
+
+    /// Creates a default `starship.toml` configuration using `starship preset`.
+    #[tracing::instrument(skip_all, fields(tool = self.name()))]
+    async fn configure(&self, context: &ProvisionContext) -> AppResult<()> {
+        let config_path = context.env_dir.join("config").join("starship.toml");
+        let starship_bin = context.env_dir.join("bin").join(self.binary_name());
+
+        // The overlay may have symlinked the user's own starship.toml; don't write through it.
+        if config_path.is_symlink() {
+            tracing::debug!(path = %config_path.display(), "Removing existing symlink for managed config file");
+            fs::remove_file(&config_path)?;
+        }
+
+        tracing::trace!(path = %config_path.display(), "Generating starship config");
+
+        let status = Command::new(&starship_bin)
+            .arg("preset")
+            .arg("no-empty-icons")
+            .arg("-o")
+            .arg(&config_path)
+            .status()
+            .await
+            .context("Failed to execute starship preset command")?;
+
+        if !status.success() {
+            return Err(anyhow!(
+                "starship preset command failed with status: {}",
+                status
+            ));
+        }
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
-use super::Tool;
+use super::{Tool, graph::Dependency};
+use crate::shell::{Shell, nu_autoload};
 
 pub struct Zoxide;
 
@@ -14,4 +15,27 @@ impl Tool for Zoxide {
     fn binary_name(&self) -> &'static str {
         "zoxide"
     }
+
+    fn dependencies(&self, shell: Shell) -> Vec<Dependency> {
+        match shell {
+            // The init script is rewritten to use fish's own functions (see below).
+            Shell::Fish => vec![Dependency::Resource("fish-share".to_string())],
+            Shell::Zsh | Shell::Nushell => Vec::new(),
+        }
+    }
+
+    fn shell_init(&self, shell: Shell) -> Option<String> {
+        let init = match shell {
+            // zoxide's init script looks for fish's functions in `$__fish_data_dir`,
+            // which points at the system install rather than the environment's fish.
+            Shell::Fish => concat!(
+                "zoxide init fish | string replace --regex \\\n",
+                "    -- '\\$__fish_data_dir' $fish_data_dir | source",
+            )
+            .to_string(),
+            Shell::Zsh => "eval \"$(zoxide init zsh)\"".to_string(),
+            Shell::Nushell => nu_autoload("zoxide", "zoxide init nushell"),
+        };
+        Some(init)
+    }
 }
@@ -0,0 +1,34 @@
+use super::{ProvisionContext, Tool};
+use crate::{error::AppResult, state::Provenance};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use indicatif::{ProgressBar, ProgressStyle};
+
+pub struct Zsh;
+
+#[async_trait]
+impl Tool for Zsh {
+    fn name(&self) -> &'static str {
+        "zsh"
+    }
+
+    fn repo(&self) -> &'static str {
+        "zsh-users/zsh"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        "zsh"
+    }
+
+    /// zsh publishes no prebuilt binaries, so it only ever comes from the system.
+    async fn provision_from_source(
+        &self,
+        _context: &ProvisionContext,
+        _pb: &ProgressBar,
+        _spinner_style: &ProgressStyle,
+    ) -> AppResult<Provenance> {
+        Err(anyhow!(
+            "zsh has no release builds to download; install it with your system's package manager"
+        ))
+    }
+}
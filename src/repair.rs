@@ -0,0 +1,83 @@
+use crate::{
+    provision::{Tool, registry::ToolRegistry},
+    shim::{self, LIBEXEC_DIR},
+    state::EnvState,
+};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Finds what is wrong with a tool's installation in `env_dir`, if anything.
+/// A tool that was never installed has nothing to repair.
+pub fn diagnose(tool: &dyn Tool, env_dir: &Path) -> Option<String> {
+    let binary = env_dir.join("bin").join(tool.binary_name());
+    let Ok(metadata) = binary.symlink_metadata() else {
+        // No binary, but files from an interrupted install may still be around.
+        return tool
+            .install_dirs()
+            .into_iter()
+            .find(|dir| env_dir.join(dir).exists())
+            .map(|dir| {
+                format!(
+                    "{}/ is present, but bin/{} is missing",
+                    dir,
+                    tool.binary_name()
+                )
+            });
+    };
+
+    // Behind a shim, it is the binary in `libexec/` that has to be intact.
+    let (binary, metadata, shown) = if shim::is_shim(&binary) {
+        let real_binary = env_dir.join(LIBEXEC_DIR).join(tool.binary_name());
+        let shown = format!("{}/{}", LIBEXEC_DIR, tool.binary_name());
+        let Ok(metadata) = real_binary.symlink_metadata() else {
+            return Some(format!(
+                "bin/{} is a shim, but {} is missing",
+                tool.binary_name(),
+                shown
+            ));
+        };
+        (real_binary, metadata, shown)
+    } else {
+        (binary, metadata, format!("bin/{}", tool.binary_name()))
+    };
+
+    if metadata.is_symlink() && !binary.exists() {
+        let target = fs::read_link(&binary)
+            .map(|target| target.display().to_string())
+            .unwrap_or_default();
+        return Some(format!("{} is a broken symlink to {}", shown, target));
+    }
+    if fs::metadata(&binary).is_ok_and(|metadata| metadata.len() == 0) {
+        return Some(format!("{} is an empty file", shown));
+    }
+
+    // A companion dir is only expected if the directory holding it was installed at all,
+    // e.g. `fish_runtime/share` is not needed for a fish symlinked from the system.
+    tool.companion_dirs().into_iter().find_map(|dir| {
+        let parent = Path::new(&dir).parent()?;
+        (env_dir.join(parent).is_dir() && !env_dir.join(&dir).is_dir())
+            .then(|| format!("{}/ is missing", dir))
+    })
+}
+
+/// The registered tools that have been installed into `env_dir`, whether intact or not.
+pub fn installed_tools(registry: &ToolRegistry, env_dir: &Path) -> Vec<Arc<dyn Tool>> {
+    let state = EnvState::load(env_dir).unwrap_or_default();
+    registry
+        .iter()
+        .filter(|tool| {
+            state.tools.contains_key(tool.name())
+                || env_dir
+                    .join("bin")
+                    .join(tool.binary_name())
+                    .symlink_metadata()
+                    .is_ok()
+                || tool
+                    .install_dirs()
+                    .iter()
+                    .any(|dir| env_dir.join(dir).exists())
+        })
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,157 @@
+use crate::{
+    activation,
+    error::AppResult,
+    state::{self, EnvState},
+};
+use anyhow::{Context, anyhow};
+use clap::ValueEnum;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How `isoterm env` prints the environment's variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum EnvFormat {
+    /// `export NAME='value'` lines, for `eval` in POSIX shells.
+    #[default]
+    Sh,
+    /// `set -gx NAME value` lines.
+    Fish,
+    /// A JSON object of names to values.
+    Json,
+    /// `NAME="value"` lines, for direnv, systemd's `EnvironmentFile` and IDEs.
+    Dotenv,
+}
+
+/// Runs `command` inside the environment in `env_dir`, and exits with its status.
+#[tracing::instrument(fields(env_dir = %env_dir.display()))]
+pub fn run(env_dir: &Path, command: &[String]) -> AppResult<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("No command given to run"))?;
+    let env_dir = resolve_environment(env_dir)?;
+    let state = EnvState::load(&env_dir)?;
+    let mut command = Command::new(program);
+    command.args(args);
+    command.envs(environment_vars(&env_dir, &state));
+    exec(command, program)
+}
+
+/// Starts the interactive shell of the environment in `env_dir`, and exits with its status.
+#[tracing::instrument(fields(env_dir = %env_dir.display()))]
+pub fn shell(env_dir: &Path) -> AppResult<()> {
+    let env_dir = resolve_environment(env_dir)?;
+    let state = EnvState::load(&env_dir)?;
+    let mut command = state.shell.interactive_command(&env_dir);
+    command.envs(environment_vars(&env_dir, &state));
+    exec(command, state.shell.tool_name())
+}
+
+/// Prints the variables `activate.sh` sets for the environment in `env_dir`. With `diff`,
+/// only those whose value differs from the current process environment are printed.
+#[tracing::instrument(fields(env_dir = %env_dir.display()))]
+pub fn print_env(env_dir: &Path, format: EnvFormat, diff: bool) -> AppResult<()> {
+    let env_dir = resolve_environment(env_dir)?;
+    let state = EnvState::load(&env_dir)?;
+    let vars: Vec<(String, String)> = environment_vars(&env_dir, &state)
+        .into_iter()
+        .filter(|(name, value)| !diff || env::var_os(name).as_ref() != Some(value))
+        .map(|(name, value)| (name, value.to_string_lossy().into_owned()))
+        .collect();
+    print!("{}", render_env(&vars, format)?);
+    Ok(())
+}
+
+/// Renders `vars` in `format`.
+fn render_env(vars: &[(String, String)], format: EnvFormat) -> AppResult<String> {
+    let rendered = match format {
+        EnvFormat::Sh => vars
+            .iter()
+            .map(|(name, value)| format!("export {}={}\n", name, activation::sh_quote(value)))
+            .collect(),
+        EnvFormat::Fish => vars
+            .iter()
+            .map(|(name, value)| {
+                // fish keeps PATH as a list, as `activate.fish` sets it.
+                let values: Vec<String> = if name == "PATH" {
+                    value.split(':').map(fish_quote).collect()
+                } else {
+                    vec![fish_quote(value)]
+                };
+                format!("set -gx {} {}\n", name, values.join(" "))
+            })
+            .collect(),
+        EnvFormat::Json => {
+            let object: serde_json::Map<String, serde_json::Value> = vars
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone().into()))
+                .collect();
+            format!("{}\n", serde_json::to_string_pretty(&object)?)
+        }
+        EnvFormat::Dotenv => vars
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"\n", name, dotenv_escape(value)))
+            .collect(),
+    };
+    Ok(rendered)
+}
+
+/// Quotes `value` as a single fish word.
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Escapes `value` for a double-quoted dotenv value, which systemd and direnv both unescape.
+fn dotenv_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The absolute path of the environment in `env_dir`, which must exist.
+fn resolve_environment(env_dir: &Path) -> AppResult<PathBuf> {
+    state::check_environment(env_dir)?;
+    fs::canonicalize(env_dir).with_context(|| format!("Failed to resolve {}", env_dir.display()))
+}
+
+/// The variables that `activate.sh` sets for the environment in `env_dir`, from the
+/// activations recorded in its state when the scripts were generated.
+fn environment_vars(env_dir: &Path, state: &EnvState) -> Vec<(String, OsString)> {
+    // Every environment has at least its shell, so none means an older environment.
+    if state.activations.is_empty() {
+        tracing::warn!(
+            "{} records no tool variables; run isoterm on it again to record them",
+            env_dir.display()
+        );
+    }
+    let vars = activation::activated_env(env_dir, &state.activations);
+    tracing::trace!(?vars, "Activated variables");
+    vars
+}
+
+/// Replaces isoterm with `command`, so that it gets the terminal and signals directly
+/// and its exit status becomes isoterm's.
+fn exec(mut command: Command, name: &str) -> AppResult<()> {
+    tracing::debug!(?command, "Running command");
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Only returns if the command could not be started.
+        let e = command.exec();
+        Err(e).with_context(|| format!("Failed to run {}", name))
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command
+            .status()
+            .with_context(|| format!("Failed to run {}", name))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
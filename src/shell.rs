@@ -0,0 +1,132 @@
+use crate::activation::Activation;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+/// The interactive shell an environment starts, and the backend that generates its rc file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Shell {
+    #[default]
+    Fish,
+    Zsh,
+    Nushell,
+}
+
+impl Shell {
+    pub const ALL: [Shell; 3] = [Shell::Fish, Shell::Zsh, Shell::Nushell];
+
+    /// The name of the tool that provides the shell.
+    pub fn tool_name(self) -> &'static str {
+        match self {
+            Shell::Fish => "fish",
+            Shell::Zsh => "zsh",
+            Shell::Nushell => "nushell",
+        }
+    }
+
+    /// Where the shell's rc file goes, relative to the environment root.
+    pub fn rc_path(self) -> &'static str {
+        match self {
+            Shell::Fish => "config/fish/config.fish",
+            Shell::Zsh => "config/zsh/.zshrc",
+            Shell::Nushell => "config/nushell/config.nu",
+        }
+    }
+
+    /// The end of `activate.sh`, which replaces the activating process with the shell.
+    pub fn render_exec(self) -> String {
+        match self {
+            Shell::Fish => concat!(
+                "# The `-C` flag sources our config, at $XDG_CONFIG_HOME/fish/config.fish.\n",
+                "exec \"$ENV_DIR/bin/fish\" -l -C \"source '$XDG_CONFIG_HOME/fish/config.fish'\"\n",
+            ),
+            Shell::Zsh => concat!(
+                "# zsh reads its rc files from ZDOTDIR, at $XDG_CONFIG_HOME/zsh/.zshrc.\n",
+                "export ZDOTDIR=\"$XDG_CONFIG_HOME/zsh\"\n",
+                "exec \"$ENV_DIR/bin/zsh\" -l\n",
+            ),
+            Shell::Nushell => concat!(
+                "# Nushell reads our config from $XDG_CONFIG_HOME/nushell/config.nu.\n",
+                "exec \"$ENV_DIR/bin/nu\" --login --config \"$XDG_CONFIG_HOME/nushell/config.nu\"\n",
+            ),
+        }
+        .to_string()
+    }
+
+    /// The command that starts the shell of the environment in `env_dir`, as `activate.sh` does.
+    pub fn interactive_command(self, env_dir: &Path) -> Command {
+        let config_home = env_dir.join("config");
+        match self {
+            Shell::Fish => {
+                let mut command = Command::new(env_dir.join("bin").join("fish"));
+                command.arg("-l").arg("-C").arg(format!(
+                    "source '{}'",
+                    config_home.join("fish").join("config.fish").display()
+                ));
+                command
+            }
+            Shell::Zsh => {
+                let mut command = Command::new(env_dir.join("bin").join("zsh"));
+                command.arg("-l").env("ZDOTDIR", config_home.join("zsh"));
+                command
+            }
+            Shell::Nushell => {
+                let mut command = Command::new(env_dir.join("bin").join("nu"));
+                command
+                    .arg("--login")
+                    .arg("--config")
+                    .arg(config_home.join("nushell").join("config.nu"));
+                command
+            }
+        }
+    }
+
+    /// Renders the shell's rc file, with the interactive initialization of each tool.
+    pub fn render_rc(self, activations: &[Activation]) -> String {
+        match self {
+            Shell::Fish => include_str!("../templates/config.fish")
+                .replace("${INIT}$\n", &render_init(activations, "    ")),
+            Shell::Zsh => include_str!("../templates/zshrc")
+                .replace("${INIT}$\n", &render_init(activations, "")),
+            Shell::Nushell => include_str!("../templates/config.nu")
+                .replace("${INIT}$\n", &render_init(activations, "")),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tool_name())
+    }
+}
+
+/// Nushell code that saves the script printed by `command` where nushell loads it on startup.
+/// Nushell cannot source a script it generates in the same file, as `fish` can with `| source`.
+pub fn nu_autoload(name: &str, command: &str) -> String {
+    format!(
+        concat!(
+            "mkdir ($nu.data-dir | path join \"vendor/autoload\")\n",
+            "^{} | save -f ($nu.data-dir | path join \"vendor/autoload/{}.nu\")",
+        ),
+        command, name
+    )
+}
+
+/// Renders the tools' interactive initialization, one tool after another.
+fn render_init(activations: &[Activation], indent: &str) -> String {
+    activations
+        .iter()
+        .filter_map(|activation| activation.shell_init.as_deref())
+        .flat_map(str::lines)
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_string()
+            } else {
+                format!("{}{}\n", indent, line)
+            }
+        })
+        .collect()
+}
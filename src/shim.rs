@@ -0,0 +1,93 @@
+use crate::{
+    activation::{Activation, render_sh_env, sh_quote},
+    error::AppResult,
+    provision::Tool,
+};
+use anyhow::Context;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// The directory, relative to the environment root, holding the real binaries behind shims.
+pub const LIBEXEC_DIR: &str = "libexec";
+
+/// The line that marks a file in `bin/` as a generated shim.
+const SHIM_MARKER: &str = "# Generated by isoterm:";
+
+/// Whether the file at `path` is a shim generated by isoterm.
+pub fn is_shim(path: &Path) -> bool {
+    // The marker is on the second line, so the start of the file is enough.
+    let mut head = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(256).read_to_end(&mut head))
+        .is_ok()
+        && String::from_utf8_lossy(&head)
+            .lines()
+            .nth(1)
+            .is_some_and(|line| line.starts_with(SHIM_MARKER))
+}
+
+/// Moves the tool's binary from `bin/` to `libexec/` and puts a shim in its place, which sets
+/// the variables the tool needs and runs it. An existing shim is rewritten.
+#[tracing::instrument(skip_all, fields(tool = tool.name()))]
+pub fn install(env_dir: &Path, tool: &dyn Tool) -> AppResult<()> {
+    let binary = env_dir.join("bin").join(tool.binary_name());
+    if !is_shim(&binary) {
+        let libexec_dir = env_dir.join(LIBEXEC_DIR);
+        fs::create_dir_all(&libexec_dir)?;
+        // `bin/` and `libexec/` are siblings, so relative symlinks still resolve after the move.
+        let real_binary = libexec_dir.join(tool.binary_name());
+        fs::rename(&binary, &real_binary).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                binary.display(),
+                real_binary.display()
+            )
+        })?;
+        tracing::debug!(path = %real_binary.display(), "Moved binary behind a shim");
+    }
+
+    let target = format!("{}/{}", LIBEXEC_DIR, tool.binary_name());
+    write(&binary, env_dir, tool, &target)
+}
+
+/// Writes an executable shim to `path` that sets the variables `tool` needs and runs
+/// `target`, a path relative to the environment root.
+pub fn write(path: &Path, env_dir: &Path, tool: &dyn Tool, target: &str) -> AppResult<()> {
+    let env_dir = fs::canonicalize(env_dir)
+        .with_context(|| format!("Failed to resolve {}", env_dir.display()))?;
+    fs::write(path, render(&env_dir, tool, target))
+        .with_context(|| format!("Failed to write shim {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    tracing::debug!(path = %path.display(), target, "Wrote shim");
+    Ok(())
+}
+
+/// Renders the shim script for `tool`.
+fn render(env_dir: &Path, tool: &dyn Tool, target: &str) -> String {
+    // The path is absolute, so the shim also works when it is symlinked elsewhere.
+    // Re-running isoterm after moving the environment rewrites it.
+    format!(
+        concat!(
+            "#!/bin/sh\n",
+            "{marker} runs {target} with the environment it needs.\n",
+            "ENV_DIR={env_dir}\n",
+            "export XDG_CONFIG_HOME=\"$ENV_DIR/config\"\n",
+            "export XDG_DATA_HOME=\"$ENV_DIR/data\"\n",
+            "{tool_env}",
+            "exec \"$ENV_DIR/{target}\" \"$@\"\n",
+        ),
+        marker = SHIM_MARKER,
+        target = target,
+        env_dir = sh_quote(&env_dir.display().to_string()),
+        tool_env = render_sh_env(&[Activation {
+            env_vars: tool.env_vars(),
+            data_dirs: tool.data_dirs(),
+            ..Default::default()
+        }]),
+    )
+}
@@ -0,0 +1,180 @@
+use crate::{activation::Activation, error::AppResult, shell::Shell};
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The file, relative to the environment root, that records how the environment was built.
+pub const STATE_FILE: &str = "isoterm.json";
+
+/// The file, relative to the environment root, that marks a directory as an isoterm environment.
+pub const MARKER_FILE: &str = ".isoterm-env";
+
+/// Marks `env_dir` as an isoterm environment.
+pub fn write_marker(env_dir: &Path) -> AppResult<()> {
+    let path = env_dir.join(MARKER_FILE);
+    fs::write(&path, "This directory is an isoterm environment.\n")
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Rejects directories that isoterm did not create as an environment, before they are
+/// changed or deleted.
+pub fn check_environment(env_dir: &Path) -> AppResult<()> {
+    if env_dir.join(MARKER_FILE).is_file() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} is not an isoterm environment",
+            env_dir.display()
+        ))
+    }
+}
+
+/// The root of the isoterm environment that `path` is inside of, if any.
+pub fn environment_containing(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join(MARKER_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Persistent state of an environment.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EnvState {
+    /// Where each provisioned tool came from, keyed by tool name.
+    #[serde(default)]
+    pub tools: BTreeMap<String, Provenance>,
+    /// Tools that failed in a run that kept the environment, with the reason, keyed by tool
+    /// name. A tool's entry is removed once it is provisioned.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub failed: BTreeMap<String, String>,
+    /// Whether the tools' binaries are behind shims, since a run with `--shims`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shims: bool,
+    /// Shims for the environment's tools on the host, outside the environment, keyed by tool name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub exposed: BTreeMap<String, PathBuf>,
+    /// The environment's interactive shell, as chosen with `--shell`.
+    #[serde(default)]
+    pub shell: Shell,
+    /// What the installed tools contribute to the activated environment, one entry per tool
+    /// in the order the activation scripts were rendered from, so `env`, `run` and `shell`
+    /// set the same.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub activations: Vec<Activation>,
+}
+
+impl EnvState {
+    /// Loads the state of the environment in `env_dir`, or an empty state if there is none yet.
+    pub fn load(env_dir: &Path) -> AppResult<Self> {
+        let path = env_dir.join(STATE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Records the activation of a tool provisioned in this run, replacing the one from an
+    /// earlier run in place. The tools a run did not select keep theirs, so the environment's
+    /// scripts still set them up.
+    pub fn record_activation(&mut self, activation: Activation) {
+        match self
+            .activations
+            .iter_mut()
+            .find(|recorded| recorded.tool == activation.tool)
+        {
+            Some(recorded) => *recorded = activation,
+            None => self.activations.push(activation),
+        }
+    }
+
+    /// Drops the activation of `tool`, once it has been removed from the environment.
+    pub fn forget_activation(&mut self, tool: &str) {
+        self.activations.retain(|recorded| recorded.tool != tool);
+    }
+
+    /// Writes the state back to the environment in `env_dir`.
+    pub fn save(&self, env_dir: &Path) -> AppResult<()> {
+        let path = env_dir.join(STATE_FILE);
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Records where a tool's binary came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    pub source: Source,
+    /// How the binary was made to run on a host without the standard loader, if it had to be.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_fix: Option<LoaderFix>,
+}
+
+impl Provenance {
+    pub fn system(path: &Path) -> Self {
+        Self {
+            source: Source::System {
+                path: path.display().to_string(),
+            },
+            loader_fix: None,
+        }
+    }
+
+    pub fn vendored(path: &Path, version: Option<String>, sha256: String) -> Self {
+        Self {
+            source: Source::Vendored {
+                path: path.display().to_string(),
+                version,
+                sha256,
+            },
+            loader_fix: None,
+        }
+    }
+
+    pub fn download(url: &str, asset: &str, sha256: String) -> Self {
+        Self {
+            source: Source::Download {
+                url: url.to_string(),
+                asset: asset.to_string(),
+                sha256,
+            },
+            loader_fix: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Source {
+    /// Symlinked from an existing installation on the system.
+    System { path: String },
+    /// Copied from an existing installation on the system, with `--vendor-system`.
+    Vendored {
+        path: String,
+        /// The first line of the binary's `--version` output, if it has one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        sha256: String,
+    },
+    /// Downloaded from a release asset.
+    Download {
+        url: String,
+        asset: String,
+        sha256: String,
+    },
+}
+
+/// The approach taken to run a binary on a non-FHS host (e.g. NixOS, Guix).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum LoaderFix {
+    /// A static (musl) build was chosen, so no loader is needed.
+    StaticBuild,
+    /// `PT_INTERP` and `RPATH` were rewritten to point at the host's loader.
+    PatchedInterpreter { interpreter: String, rpath: String },
+}
@@ -0,0 +1,64 @@
+# This script activates the environment in the current fish session, in place:
+#
+#     source activate.fish
+#
+# It prepends the environment to PATH, points the XDG variables into it and marks
+# the prompt. Run `deactivate` to restore the previous values.
+
+# Leave an environment that is already active in this shell first. The saved PATH is not
+# exported, so it is only set where `deactivate` is defined.
+if set -q _isoterm_old_PATH
+    deactivate
+end
+
+# Determine the absolute path to the environment's root directory.
+set -l ENV_DIR (builtin realpath (dirname (status filename)))
+if not test -f "$ENV_DIR/.isoterm-env"
+    set ENV_DIR ${ENV_DIR}$
+end
+
+# Saves the variable named $name, or that it was unset, for `deactivate`.
+function _isoterm_save -a name
+    if set -q $name
+        set -g _isoterm_old_$name $$name
+    end
+end
+
+# Puts back what `_isoterm_save` saved for the variable named $name.
+function _isoterm_restore -a name
+    set -l old _isoterm_old_$name
+    if set -q $old
+        set -gx $name $$old
+        set -e $old
+    else
+        set -e $name
+    end
+end
+
+function deactivate
+${RESTORE}$
+    set -e ISOTERM_ENV
+    if functions -q _isoterm_old_fish_prompt
+        functions -e fish_prompt
+        functions -c _isoterm_old_fish_prompt fish_prompt
+        functions -e _isoterm_old_fish_prompt
+    end
+    functions -e _isoterm_save _isoterm_restore deactivate
+end
+
+${SAVE}$
+set -gx ISOTERM_ENV $ENV_DIR
+
+${PATH}$
+set -gx XDG_CONFIG_HOME "$ENV_DIR/config"
+set -gx XDG_DATA_HOME "$ENV_DIR/data"
+${TOOL_ENV}$
+
+# Mark the prompt, keeping whatever prompt is already in use.
+if functions -q fish_prompt
+    functions -c fish_prompt _isoterm_old_fish_prompt
+    function fish_prompt
+        printf '(isoterm) '
+        _isoterm_old_fish_prompt
+    end
+end
@@ -0,0 +1,64 @@
+# This script activates the environment in the current ${SHELL}$ session, in place:
+#
+#     source activate.${SHELL}$
+#
+# It prepends the environment to PATH, points the XDG variables into it and marks
+# the prompt. Run `deactivate` to restore the previous values.
+
+# Leave an environment that is already active in this shell first. The saved PATH is not
+# exported, so it is only set where `deactivate` is defined.
+if [ -n "${_ISOTERM_OLD_PATH+set}" ]; then
+  deactivate
+fi
+
+# Determine the absolute path to the environment's root directory.
+if [ -n "${BASH_SOURCE:-}" ]; then
+  _isoterm_script="$BASH_SOURCE"
+elif [ -n "${ZSH_VERSION:-}" ]; then
+  # In eval, so that other shells never have to parse zsh's syntax.
+  eval '_isoterm_script="${(%):-%x}"'
+fi
+ENV_DIR=$(cd "$(dirname "${_isoterm_script:-.}")" 2>/dev/null && pwd)
+if [ ! -f "$ENV_DIR/.isoterm-env" ]; then
+  ENV_DIR=${ENV_DIR}$
+fi
+unset _isoterm_script
+
+# Saves the variable named $1, or that it was unset, for `deactivate`.
+_isoterm_save () {
+  if eval "[ -n \"\${$1+set}\" ]"; then
+    eval "_ISOTERM_OLD_$1=\"\$$1\""
+  fi
+}
+
+# Puts back what `_isoterm_save` saved for the variable named $1.
+_isoterm_restore () {
+  if eval "[ -n \"\${_ISOTERM_OLD_$1+set}\" ]"; then
+    eval "$1=\"\$_ISOTERM_OLD_$1\""
+    unset "_ISOTERM_OLD_$1"
+  else
+    unset "$1"
+  fi
+}
+
+deactivate () {
+${RESTORE}$
+  _isoterm_restore PS1
+  unset ISOTERM_ENV
+  hash -r 2>/dev/null
+  unset -f _isoterm_save _isoterm_restore deactivate
+}
+
+${SAVE}$
+_isoterm_save PS1
+export ISOTERM_ENV="$ENV_DIR"
+
+${PATH}$
+export XDG_CONFIG_HOME="$ENV_DIR/config"
+export XDG_DATA_HOME="$ENV_DIR/data"
+${TOOL_ENV}$
+PS1="(isoterm) ${PS1:-}"
+
+# Forget command locations remembered from before PATH changed.
+hash -r 2>/dev/null
+unset ENV_DIR
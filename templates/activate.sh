@@ -1,14 +1,29 @@
 #!/bin/sh
 
-# This script sets up the necessary environment variables and executes the fish shell.
-
-set -e
-
-# Determine the absolute path to the environment's root directory.
-ENV_DIR=$(cd "$(dirname "$0")" && pwd)
+# This script sets up the necessary environment variables and executes the environment's shell.
+# It works both when executed and when sourced from bash, zsh or a POSIX sh. It does not
+# change shell options, since when sourced they would stay set in the calling shell.
+
+# Determine the absolute path to the environment's root directory. When sourced, `$0` is
+# the calling shell, so ask the shell for the script's path where it can tell.
+if [ -n "${BASH_SOURCE:-}" ]; then
+  _isoterm_script="$BASH_SOURCE"
+elif [ -n "${ZSH_VERSION:-}" ]; then
+  # In eval, so that POSIX shells never have to parse zsh's syntax.
+  eval '_isoterm_script="${(%):-%x}"'
+else
+  _isoterm_script="$0"
+fi
+ENV_DIR=$(cd "$(dirname "$_isoterm_script")" 2>/dev/null && pwd)
+# A POSIX sh cannot tell a sourced script where it is; fall back to where it was created.
+if [ ! -f "$ENV_DIR/.isoterm-env" ]; then
+  ENV_DIR=${ENV_DIR}$
+fi
+unset _isoterm_script
 
-# 1. PATH: Prepend the environment's bin directory to the system PATH.
-export PATH="$ENV_DIR/bin:$PATH"
+# 1. PATH: Prepend the environment's bin directory, and any directories the
+#    installed tools add, to the system PATH.
+${PATH}$
 
 # 2. XDG_CONFIG_HOME & XDG_DATA_HOME: Redirect all configuration and data
 #    lookups for XDG-compliant tools (Helix, Atuin, Starship) into the
@@ -16,26 +31,10 @@ export PATH="$ENV_DIR/bin:$PATH"
 export XDG_CONFIG_HOME="$ENV_DIR/config"
 export XDG_DATA_HOME="$ENV_DIR/data"
 
-# 3. HELIX_RUNTIME: Point Helix to its runtime files (themes, grammars).
-#    This is set ONLY if a local runtime directory exists within the env.
-#    If it doesn't exist, the variable is left unset, allowing Helix to
-#    fall back to a user-wide installation (the intended behavior for symlinks).
-if [ -d "$ENV_DIR/helix/runtime" ]; then
-  export HELIX_RUNTIME="$ENV_DIR/helix/runtime"
-fi
-
-# 4. XDG_DATA_DIRS: Prepend the portable fish shell's runtime data directory.
-#    This is distinct from XDG_DATA_HOME and is critical for allowing fish
-#    to find its standard library functions and completions.
-FISH_RUNTIME_DATA_DIR="$ENV_DIR/fish_runtime/share"
-if [ -n "$XDG_DATA_DIRS" ]; then
-  export XDG_DATA_DIRS="$FISH_RUNTIME_DATA_DIR:$XDG_DATA_DIRS"
-else
-  # Provide a sensible default if the variable is not already set.
-  export XDG_DATA_DIRS="$FISH_RUNTIME_DATA_DIR:/usr/local/share:/usr/share"
-fi
+# 3. Tool environment: variables and XDG_DATA_DIRS entries contributed by the
+#    installed tools, such as HELIX_RUNTIME or fish's runtime data directory.
+${TOOL_ENV}$
 
-# 5. Execute fish: Replace the current shell process with fish.
-#    The `-C` flag executes a command, in this case sourcing our custom config,
-#    which will be located at $XDG_CONFIG_HOME/fish/config.fish.
-exec "$ENV_DIR/bin/fish" -l -C "source '$XDG_CONFIG_HOME/fish/config.fish'"
+# 4. Execute the shell: Replace the current shell process with the environment's
+#    shell, started with our custom config.
+${EXEC}$
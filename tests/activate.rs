@@ -0,0 +1,227 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Creates an environment whose tools are dummy scripts. The dummy fish reports the
+/// environment it was started in, instead of starting a shell.
+fn create_environment() -> common::TestEnv {
+    create_environment_with(&[])
+}
+
+/// Like [`create_environment`], passing `args` to isoterm. The dummy zsh reports its `ZDOTDIR`.
+fn create_environment_with(args: &[&str]) -> common::TestEnv {
+    common::create_environment(
+        &[
+            (
+                "fish",
+                "#!/bin/sh\necho \"XDG_CONFIG_HOME=$XDG_CONFIG_HOME\"\n",
+            ),
+            ("zsh", "#!/bin/sh\necho \"ZDOTDIR=$ZDOTDIR\"\n"),
+        ],
+        args,
+    )
+}
+
+/// The shells to test with, among those installed.
+fn available_shells() -> Vec<&'static str> {
+    ["sh", "bash", "dash", "zsh"]
+        .into_iter()
+        .filter(|shell| which::which(shell).is_ok())
+        .collect()
+}
+
+/// Runs `script` with `shell -c` from an unrelated working directory, returning its stdout.
+fn run_shell(shell: &str, script: &str) -> String {
+    let output = Command::new(shell)
+        .arg("-c")
+        .arg(script)
+        .current_dir(std::env::temp_dir())
+        .output()
+        .expect("Failed to run shell");
+    String::from_utf8(output.stdout).expect("Failed to parse stdout")
+}
+
+fn expected_output(env_dir: &Path) -> String {
+    let env_dir = fs::canonicalize(env_dir).unwrap();
+    format!("XDG_CONFIG_HOME={}/config\n", env_dir.display())
+}
+
+#[test]
+fn test_activate_when_sourced() {
+    let env = create_environment();
+    let activate = env.path().join("activate.sh");
+    for shell in available_shells() {
+        let output = run_shell(shell, &format!(". '{}'", activate.display()));
+        assert_eq!(
+            output,
+            expected_output(env.path()),
+            "sourced from {}",
+            shell
+        );
+    }
+}
+
+#[test]
+fn test_activate_when_executed() {
+    let env = create_environment();
+    let activate = env.path().join("activate.sh");
+    for shell in available_shells() {
+        let output = run_shell(shell, &format!("{} '{}'", shell, activate.display()));
+        assert_eq!(output, expected_output(env.path()), "executed by {}", shell);
+    }
+}
+
+#[test]
+fn test_activate_after_moving_environment() {
+    let env = create_environment();
+    let moved_env = env.temp_dir().join("moved");
+    fs::rename(env.path(), &moved_env).expect("Failed to move environment");
+    let activate = moved_env.join("activate.sh");
+
+    // Shells that can locate a sourced or executed script find the environment's new place.
+    let mut cases = vec![("sh", format!("sh '{}'", activate.display()))];
+    for shell in ["bash", "zsh"] {
+        if available_shells().contains(&shell) {
+            cases.push((shell, format!(". '{}'", activate.display())));
+        }
+    }
+    for (shell, script) in cases {
+        let output = run_shell(shell, &script);
+        assert_eq!(output, expected_output(&moved_env), "{}: {}", shell, script);
+    }
+}
+
+#[test]
+fn test_activate_does_not_change_shell_options() {
+    if !available_shells().contains(&"bash") {
+        return;
+    }
+    let env = create_environment();
+    // Bash lets a function shadow `exec`, which shows the options the script leaves behind.
+    let script = format!(
+        "before=$-; exec() {{ echo \"$before $-\"; }}; . '{}'",
+        env.path().join("activate.sh").display()
+    );
+    let output = run_shell("bash", &script);
+    let (before, after) = output.trim().split_once(' ').unwrap();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn test_activate_in_place_and_deactivate() {
+    let env = create_environment();
+    let expected = expected_output(env.path());
+    for shell in ["bash", "zsh"] {
+        if !available_shells().contains(&shell) {
+            continue;
+        }
+        let activate = env.path().join(format!("activate.{}", shell));
+        let script = format!(
+            concat!(
+                "export XDG_CONFIG_HOME=/original; PS1='$ '; path=\"$PATH\"\n",
+                ". '{0}'\n",
+                "echo \"XDG_CONFIG_HOME=$XDG_CONFIG_HOME\"; echo \"$PS1\"\n",
+                // Activating again must not save the activated values as the originals.
+                ". '{0}'\n",
+                "deactivate\n",
+                "echo \"XDG_CONFIG_HOME=$XDG_CONFIG_HOME\"; echo \"$PS1\"\n",
+                "[ \"$PATH\" = \"$path\" ] && echo 'PATH restored'\n",
+                "echo \"XDG_DATA_HOME=${{XDG_DATA_HOME-unset}}\"\n",
+            ),
+            activate.display()
+        );
+        let output = run_shell(shell, &script);
+        assert_eq!(
+            output,
+            format!(
+                "{}(isoterm) $ \nXDG_CONFIG_HOME=/original\n$ \nPATH restored\nXDG_DATA_HOME=unset\n",
+                expected
+            ),
+            "{}",
+            shell
+        );
+    }
+
+    if which::which("fish").is_ok() {
+        let script = format!(
+            concat!(
+                "set -gx XDG_CONFIG_HOME /original; function fish_prompt; echo -n '$ '; end\n",
+                "set -g path (string join : $PATH)\n",
+                "source '{0}'\n",
+                "echo \"XDG_CONFIG_HOME=$XDG_CONFIG_HOME\"; fish_prompt; echo\n",
+                "source '{0}'\n",
+                "deactivate\n",
+                "echo \"XDG_CONFIG_HOME=$XDG_CONFIG_HOME\"; fish_prompt; echo\n",
+                "test (string join : $PATH) = \"$path\"; and echo 'PATH restored'\n",
+                "set -q XDG_DATA_HOME; or echo 'XDG_DATA_HOME=unset'\n",
+            ),
+            env.path().join("activate.fish").display()
+        );
+        // Without the user's config, which could change the prompt or the variables.
+        let output = Command::new("fish")
+            .args(["--no-config", "-c", &script])
+            .current_dir(std::env::temp_dir())
+            .output()
+            .expect("Failed to run fish");
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!(
+                "{}(isoterm) $ \nXDG_CONFIG_HOME=/original\n$ \nPATH restored\nXDG_DATA_HOME=unset\n",
+                expected
+            ),
+            "fish"
+        );
+    }
+}
+
+#[test]
+fn test_activate_with_zsh_shell() {
+    let env = create_environment_with(&["--shell", "zsh"]);
+    let output = run_shell(
+        "sh",
+        &format!("sh '{}'", env.path().join("activate.sh").display()),
+    );
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+    assert_eq!(
+        output,
+        format!("ZDOTDIR={}/config/zsh\n", env_dir.display())
+    );
+
+    let zshrc = fs::read_to_string(env.path().join("config/zsh/.zshrc")).unwrap();
+    assert!(zshrc.contains("eval \"$(starship init zsh)\""), "{}", zshrc);
+    assert!(zshrc.contains("eval \"$(zoxide init zsh)\""), "{}", zshrc);
+    assert!(!env.path().join("config/fish/config.fish").exists());
+}
+
+#[test]
+fn test_rerun_keeps_tools_outside_selection() {
+    let env = create_environment_with(&["--skip", "starship"]);
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg(env.path())
+        .args(["--only", "fish,starship"])
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(output.status.success(), "{:?}", output);
+
+    // Set up by the first run only, by the second run only, and by both.
+    let config = fs::read_to_string(env.path().join("config/fish/config.fish")).unwrap();
+    assert!(config.contains("zoxide init fish"), "{}", config);
+    assert!(config.contains("starship init fish"), "{}", config);
+    let activate = fs::read_to_string(env.path().join("activate.sh")).unwrap();
+    assert!(activate.contains("HELIX_RUNTIME"), "{}", activate);
+
+    let state = fs::read_to_string(env.path().join("isoterm.json")).unwrap();
+    let state: serde_json::Value = serde_json::from_str(&state).unwrap();
+    let tools: Vec<&str> = state["activations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|activation| activation["tool"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        tools,
+        ["fish", "zoxide", "atuin", "ripgrep", "helix", "starship"]
+    );
+}
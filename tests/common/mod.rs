@@ -0,0 +1,102 @@
+// Each test crate uses a different part of this module.
+#![allow(dead_code)]
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::{TempDir, tempdir};
+
+/// The tools every test environment has, as dummy scripts that do nothing.
+const DUMMY_TOOLS: &[&str] = &["fish", "starship", "zoxide", "atuin", "rg", "hx"];
+
+/// An environment directory inside a temporary directory, deleted on drop. The environment
+/// is a subdirectory, so files isoterm puts next to it (e.g. its lock) are cleaned up too.
+pub struct TestEnv {
+    dir: TempDir,
+    path: PathBuf,
+}
+
+impl TestEnv {
+    /// The environment directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The temporary directory holding the environment, for other test files.
+    pub fn temp_dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Creates the directory `name` next to the environment, holding `scripts` as
+    /// executables, to stand in for a directory of system binaries on the PATH.
+    pub fn host_bin(&self, name: &str, scripts: &[(&str, &str)]) -> PathBuf {
+        let dir = self.temp_dir().join(name);
+        fs::create_dir_all(&dir).expect("Failed to create host bin dir");
+        for (tool, script) in scripts {
+            write_script(&dir.join(tool), script);
+        }
+        dir
+    }
+}
+
+/// The test's PATH with `dirs` in front of it.
+pub fn path_with(dirs: &[&Path]) -> OsString {
+    let path = env::var_os("PATH").unwrap_or_default();
+    let dirs = dirs
+        .iter()
+        .map(|dir| dir.to_path_buf())
+        .chain(env::split_paths(&path));
+    env::join_paths(dirs).expect("Failed to build PATH")
+}
+
+/// Lays out an environment directory whose tools are dummy scripts, without running
+/// isoterm. `scripts` replaces the scripts of the named tools, or adds more tools.
+pub fn dummy_environment(scripts: &[(&str, &str)]) -> TestEnv {
+    let dir = tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("env");
+    let bin_dir = path.join("bin");
+    fs::create_dir_all(&bin_dir).expect("Failed to create test bin dir");
+
+    // A dummy `share` directory keeps fish from downloading its runtime.
+    fs::create_dir_all(path.join("fish_runtime").join("share"))
+        .expect("Failed to create dummy fish share dir");
+
+    let defaults = DUMMY_TOOLS
+        .iter()
+        .filter(|tool| !scripts.iter().any(|(name, _)| name == *tool))
+        // Not empty: a zero-byte binary counts as broken and would be re-provisioned.
+        .map(|tool| (*tool, "#!/bin/sh\nexit 0\n"));
+    for (tool, script) in defaults.chain(scripts.iter().copied()) {
+        write_script(&bin_dir.join(tool), script);
+    }
+    TestEnv { dir, path }
+}
+
+/// Creates an environment with [`dummy_environment`], passing `args` to isoterm.
+pub fn create_environment(scripts: &[(&str, &str)], args: &[&str]) -> TestEnv {
+    let env = dummy_environment(scripts);
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg(env.path())
+        .args(args)
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(
+        output.status.success(),
+        "isoterm failed to create the environment: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    env
+}
+
+/// Writes an executable script to `path`.
+pub fn write_script(path: &Path, script: &str) {
+    fs::write(path, script).expect("Failed to create dummy tool file");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .expect("Failed to set permissions on dummy tool");
+    }
+}
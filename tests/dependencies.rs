@@ -0,0 +1,88 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Runs isoterm on the environment with the tools declared in `tools_toml`.
+fn isoterm_with_tools(env_dir: &Path, tools_toml: &str, args: &[&str]) -> Output {
+    let tools_file = env_dir.with_file_name("tools.toml");
+    fs::write(&tools_file, tools_toml).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg(env_dir)
+        .arg("--tools-file")
+        .arg(&tools_file)
+        .args(args)
+        .output()
+        .expect("Failed to execute isoterm")
+}
+
+#[test]
+fn test_cycle_reports_only_its_tools() {
+    let env = common::dummy_environment(&[]);
+    let tools = r#"
+        [[tools]]
+        name = "x"
+        repo = "example/x"
+        depends-on = ["y"]
+
+        [[tools]]
+        name = "y"
+        repo = "example/y"
+        depends-on = ["x"]
+
+        [[tools]]
+        name = "z"
+        repo = "example/z"
+        depends-on = ["x"]
+    "#;
+
+    let output = isoterm_with_tools(env.path(), tools, &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Tool dependencies form a cycle: x -> y -> x")
+            || stderr.contains("Tool dependencies form a cycle: y -> x -> y"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("z"), "{}", stderr);
+}
+
+#[test]
+fn test_failed_dependency_blocks_its_dependents() {
+    let env = common::dummy_environment(&[("fd", "#!/bin/sh\nexit 0\n")]);
+    fs::remove_file(env.path().join("bin/rg")).unwrap();
+    let tools = r#"
+        [[tools]]
+        name = "fd"
+        repo = "example/fd"
+        depends-on = ["ripgrep"]
+    "#;
+
+    // No system rg is new enough, and ripgrep may not be downloaded, so it fails offline.
+    let output = isoterm_with_tools(
+        env.path(),
+        tools,
+        &[
+            "--tool-source",
+            "ripgrep=system-only",
+            "--min-version",
+            "ripgrep=999.0",
+        ],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("fd: not provisioned because its dependency ripgrep failed"),
+        "{}",
+        stderr
+    );
+    // fd never started, so it keeps its binary, and is retried with ripgrep on the next run.
+    assert!(env.path().join("bin/fd").exists());
+    let state: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(env.path().join("isoterm.json")).unwrap())
+            .unwrap();
+    assert!(state["failed"]["fd"].is_string());
+    assert!(state["failed"]["ripgrep"].is_string());
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// The dummy rg reports the config directory it was started with.
+const RG_SCRIPT: &str = "#!/bin/sh\necho \"XDG_CONFIG_HOME=$XDG_CONFIG_HOME\"\n";
+
+fn isoterm(args: &[&str], env_dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .args(args)
+        .arg("--env-dir")
+        .arg(env_dir)
+        .output()
+        .expect("Failed to execute isoterm")
+}
+
+#[test]
+fn test_expose_and_unexpose() {
+    let env = common::create_environment(&[("rg", RG_SCRIPT)], &[]);
+    let bin_dir = env.temp_dir().join("host-bin");
+    fs::create_dir(&bin_dir).unwrap();
+    let shim = bin_dir.join("rg");
+
+    let output = isoterm(
+        &["expose", "ripgrep", "--bin-dir", bin_dir.to_str().unwrap()],
+        env.path(),
+    );
+    assert!(output.status.success(), "expose failed: {:?}", output);
+    let output = Command::new(&shim)
+        .output()
+        .expect("Failed to run the shim");
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("XDG_CONFIG_HOME={}/config\n", env_dir.display())
+    );
+
+    let output = isoterm(&["unexpose", "ripgrep"], env.path());
+    assert!(output.status.success(), "unexpose failed: {:?}", output);
+    assert!(!shim.exists(), "the shim was not removed");
+}
+
+#[test]
+fn test_expose_refuses_to_overwrite_foreign_file() {
+    let env = common::create_environment(&[], &[]);
+    let bin_dir = env.temp_dir().join("host-bin");
+    fs::create_dir(&bin_dir).unwrap();
+    let foreign = bin_dir.join("rg");
+    fs::write(&foreign, "not isoterm's\n").unwrap();
+
+    let output = isoterm(
+        &["expose", "ripgrep", "--bin-dir", bin_dir.to_str().unwrap()],
+        env.path(),
+    );
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("Refusing to overwrite")
+    );
+    assert_eq!(fs::read_to_string(&foreign).unwrap(), "not isoterm's\n");
+}
+
+#[test]
+fn test_remove_cleans_up_exposed_shims() {
+    let env = common::create_environment(&[], &[]);
+    let bin_dir = env.temp_dir().join("host-bin");
+    fs::create_dir(&bin_dir).unwrap();
+
+    let output = isoterm(
+        &["expose", "ripgrep", "--bin-dir", bin_dir.to_str().unwrap()],
+        env.path(),
+    );
+    assert!(output.status.success(), "expose failed: {:?}", output);
+    assert!(bin_dir.join("rg").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("remove")
+        .arg(env.path())
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(output.status.success(), "remove failed: {:?}", output);
+    assert!(!env.path().exists(), "the environment was not removed");
+    assert!(!bin_dir.join("rg").exists(), "the shim was not removed");
+}
+
+#[test]
+fn test_remove_refuses_directory_without_marker() {
+    // Looks like an environment, but isoterm never created it.
+    let env = common::dummy_environment(&[]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("remove")
+        .arg(env.path())
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("is not an isoterm environment")
+    );
+    assert!(env.path().join("bin").join("rg").exists());
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::thread;
+use std::time::Duration;
+
+fn isoterm(env_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg(env_dir)
+        .args(args)
+        .output()
+        .expect("Failed to execute isoterm")
+}
+
+fn lock_path(env: &common::TestEnv) -> PathBuf {
+    env.temp_dir().join("env.lock")
+}
+
+/// Takes the environment's lock, as another isoterm run would.
+fn hold_lock(env: &common::TestEnv) -> File {
+    let file = File::create(lock_path(env)).unwrap();
+    file.try_lock().expect("Failed to take the lock");
+    file
+}
+
+#[test]
+fn test_run_gives_up_on_a_held_lock() {
+    let env = common::dummy_environment(&[]);
+    let _held = hold_lock(&env);
+
+    let output = isoterm(env.path(), &["--lock-timeout", "1"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("Another isoterm run")
+    );
+    // It never got to change the environment.
+    assert!(!env.path().join("isoterm.json").exists());
+}
+
+#[test]
+fn test_run_waits_for_the_lock() {
+    let env = common::dummy_environment(&[]);
+    let held = hold_lock(&env);
+    let releaser = thread::spawn(move || {
+        thread::sleep(Duration::from_secs(1));
+        drop(held);
+    });
+
+    let output = isoterm(env.path(), &["--lock-timeout", "30"]);
+    releaser.join().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("Waiting for another isoterm run")
+    );
+}
+
+#[test]
+fn test_remove_deletes_the_lock_file() {
+    let env = common::create_environment(&[], &[]);
+    assert!(lock_path(&env).exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("remove")
+        .arg(env.path())
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!lock_path(&env).exists());
+}
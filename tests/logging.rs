@@ -1,38 +1,17 @@
-use std::fs;
+mod common;
+
 use std::io::Read;
 use std::process::{Command, Stdio};
 use std::thread;
-use tempfile::tempdir;
 
 fn run_isoterm_with_args(args: &[&str]) -> (String, String) {
     let bin_path = env!("CARGO_BIN_EXE_isoterm");
-    let dest_dir_temp = tempdir().expect("Failed to create temp dir");
-    let dest_dir = dest_dir_temp.path();
-    let bin_dir = dest_dir.join("bin");
-    fs::create_dir_all(&bin_dir).expect("Failed to create test bin dir");
-
-    // Pre-create dummy tool files to prevent the app from trying to download them.
-    let dummy_tools = ["fish", "starship", "zoxide", "atuin", "rg", "hx"];
-    for tool in &dummy_tools {
-        let tool_path = bin_dir.join(tool);
-        fs::write(&tool_path, "").expect("Failed to create dummy tool file");
-        // On Unix, make the dummy file executable to mimic a real binary.
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            fs::set_permissions(&tool_path, fs::Permissions::from_mode(0o755))
-                .expect("Failed to set permissions on dummy tool");
-        }
-    }
-
-    // Special setup for fish: create a dummy `share` directory to prevent
-    // the `provision_source_share` function from triggering a real download.
-    let fish_runtime_dir = dest_dir.join("fish_runtime").join("share");
-    fs::create_dir_all(&fish_runtime_dir).expect("Failed to create dummy fish share dir");
+    // Pre-created dummy tools keep the app from trying to download them.
+    let env = common::dummy_environment(&[]);
 
     let mut child = Command::new(bin_path)
         .args(args)
-        .arg(dest_dir.to_str().unwrap())
+        .arg(env.path())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
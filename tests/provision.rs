@@ -0,0 +1,225 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Runs isoterm on the environment with `path_dirs` in front of the PATH.
+fn isoterm(env_dir: &Path, path_dirs: &[&Path], args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg(env_dir)
+        .args(args)
+        .env("PATH", common::path_with(path_dirs))
+        .output()
+        .expect("Failed to execute isoterm")
+}
+
+/// A system `rg` that reports `version`.
+fn rg_script(version: &str) -> String {
+    format!("#!/bin/sh\necho 'ripgrep {}'\n", version)
+}
+
+/// The system binary that the environment's `rg` is linked to.
+fn linked_rg(env_dir: &Path) -> PathBuf {
+    fs::canonicalize(env_dir.join("bin/rg")).unwrap()
+}
+
+fn read_state(env_dir: &Path) -> serde_json::Value {
+    let content = fs::read_to_string(env_dir.join("isoterm.json")).unwrap();
+    serde_json::from_str(&content).unwrap()
+}
+
+#[test]
+fn test_rerun_applies_raised_min_version() {
+    let env = common::dummy_environment(&[]);
+    fs::remove_file(env.path().join("bin/rg")).unwrap();
+    let old = env.host_bin("old", &[("rg", &rg_script("13.0.0"))]);
+    let new = env.host_bin("new", &[("rg", &rg_script("14.1.0"))]);
+
+    let output = isoterm(env.path(), &[&old, &new], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(linked_rg(env.path()), old.join("rg"));
+
+    let output = isoterm(
+        env.path(),
+        &[&old, &new],
+        &["--min-version", "ripgrep=14.0"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(linked_rg(env.path()), new.join("rg"));
+    assert_eq!(
+        read_state(env.path())["tools"]["ripgrep"]["source"]["path"],
+        new.join("rg").display().to_string()
+    );
+}
+
+#[test]
+fn test_rerun_applies_changed_source_policy() {
+    let env = common::dummy_environment(&[]);
+    let host = env.host_bin("host", &[("rg", &rg_script("14.1.0"))]);
+    let output = isoterm(env.path(), &[], &[]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // Record the dummy rg as downloaded, as a run with `--tool-source ripgrep=download-only` would.
+    let mut state = read_state(env.path());
+    state["tools"]["ripgrep"] = serde_json::json!({
+        "source": {
+            "kind": "download",
+            "url": "https://example.com/rg.tar.gz",
+            "asset": "rg.tar.gz",
+            "sha256": "0",
+        }
+    });
+    fs::write(env.path().join("isoterm.json"), state.to_string()).unwrap();
+
+    let output = isoterm(
+        env.path(),
+        &[&host],
+        &["--tool-source", "ripgrep=system-only"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(linked_rg(env.path()), host.join("rg"));
+    assert_eq!(
+        read_state(env.path())["tools"]["ripgrep"]["source"]["kind"],
+        "system"
+    );
+}
+
+#[test]
+fn test_failed_rerun_keeps_the_environment_consistent() {
+    let env = common::dummy_environment(&[]);
+    fs::remove_file(env.path().join("bin/zoxide")).unwrap();
+    let host = env.host_bin("host", &[("zoxide", "#!/bin/sh\necho 'zoxide 0.9.4'\n")]);
+    let config_fish = env.path().join("config/fish/config.fish");
+
+    let output = isoterm(env.path(), &[&host], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(fs::read_to_string(&config_fish).unwrap().contains("zoxide"));
+
+    // No system zoxide is new enough, and it may not be downloaded.
+    let output = isoterm(
+        env.path(),
+        &[&host],
+        &[
+            "--tool-source",
+            "zoxide=system-only",
+            "--min-version",
+            "zoxide=999.0",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(env.path().join("bin/rg").exists());
+    assert!(env.path().join("bin/zoxide").symlink_metadata().is_err());
+    // The configuration no longer starts the zoxide that was removed.
+    assert!(!fs::read_to_string(&config_fish).unwrap().contains("zoxide"));
+    let state = read_state(env.path());
+    assert!(state["tools"].get("zoxide").is_none());
+    assert!(
+        state["failed"]["zoxide"]
+            .as_str()
+            .unwrap()
+            .contains("no usable zoxide")
+    );
+    assert!(
+        !state["activations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|activation| activation["tool"] == "zoxide")
+    );
+
+    let output = isoterm(env.path(), &[&host], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(fs::read_to_string(&config_fish).unwrap().contains("zoxide"));
+    assert!(read_state(env.path()).get("failed").is_none());
+}
+
+#[test]
+fn test_failed_configure_hook_keeps_the_binary() {
+    let env = common::dummy_environment(&[]);
+    let output = isoterm(env.path(), &[], &[]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // atuin's configure hook cannot create its data directory over a file.
+    let data_dir = env.path().join("data/atuin");
+    fs::remove_dir_all(&data_dir).unwrap();
+    fs::write(&data_dir, "").unwrap();
+    let output = isoterm(env.path(), &[], &[]);
+    assert!(!output.status.success());
+    assert!(env.path().join("bin/atuin").exists());
+    assert!(
+        read_state(env.path())["failed"]["atuin"]
+            .as_str()
+            .unwrap()
+            .contains("Failed to configure atuin")
+    );
+
+    fs::remove_file(&data_dir).unwrap();
+    let output = isoterm(env.path(), &[], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(read_state(env.path()).get("failed").is_none());
+}
+
+#[test]
+fn test_other_environment_on_path_is_skipped() {
+    let env = common::dummy_environment(&[]);
+    fs::remove_file(env.path().join("bin/rg")).unwrap();
+    // Another environment, activated in the shell isoterm runs from.
+    let other = env.temp_dir().join("other");
+    let other_bin = env.host_bin("other/bin", &[("rg", &rg_script("14.1.0"))]);
+    fs::write(other.join(".isoterm-env"), "").unwrap();
+    let host = env.host_bin("host", &[("rg", &rg_script("14.1.0"))]);
+
+    let output = isoterm(env.path(), &[&other_bin, &host], &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(linked_rg(env.path()), host.join("rg"));
+}
+
+#[test]
+fn test_vendor_system_copies_the_binary() {
+    let env = common::dummy_environment(&[]);
+    fs::remove_file(env.path().join("bin/rg")).unwrap();
+    let host = env.host_bin("host", &[("rg", &rg_script("14.1.0"))]);
+
+    let output = isoterm(env.path(), &[&host], &["--vendor-system"]);
+    assert!(output.status.success(), "{:?}", output);
+    let rg = env.path().join("bin/rg");
+    assert!(rg.symlink_metadata().unwrap().is_file());
+    assert_eq!(
+        fs::read_to_string(&rg).unwrap(),
+        fs::read_to_string(host.join("rg")).unwrap()
+    );
+    let source = &read_state(env.path())["tools"]["ripgrep"]["source"];
+    assert_eq!(source["kind"], "vendored");
+    assert_eq!(source["path"], host.join("rg").display().to_string());
+    assert_eq!(source["sha256"].as_str().unwrap().len(), 64);
+}
+
+#[test]
+fn test_shims_run_the_binary_in_libexec() {
+    let env = common::dummy_environment(&[]);
+    fs::remove_file(env.path().join("bin/rg")).unwrap();
+    let script = "#!/bin/sh\n\
+                  [ \"$1\" = --version ] && echo 'ripgrep 14.1.0' && exit\n\
+                  echo \"XDG_CONFIG_HOME=$XDG_CONFIG_HOME\"\n";
+    let host = env.host_bin("host", &[("rg", script)]);
+
+    let output = isoterm(env.path(), &[&host], &["--shims"]);
+    assert!(output.status.success(), "{:?}", output);
+    let real = env.path().join("libexec/rg");
+    assert_eq!(fs::canonicalize(&real).unwrap(), host.join("rg"));
+    let shim = env.path().join("bin/rg");
+    assert!(!shim.symlink_metadata().unwrap().is_symlink());
+    assert!(fs::read_to_string(&shim).unwrap().contains("libexec"));
+
+    // Run from outside an activated shell, the shim still sets the environment up.
+    let output = Command::new(&shim)
+        .env_remove("XDG_CONFIG_HOME")
+        .output()
+        .expect("Failed to run the shim");
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("XDG_CONFIG_HOME={}/config\n", env_dir.display())
+    );
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_repair_relinks_a_dangling_symlink() {
+    let env = common::create_environment(&[], &[]);
+    let rg = env.path().join("bin/rg");
+    fs::remove_file(&rg).unwrap();
+    std::os::unix::fs::symlink(env.temp_dir().join("uninstalled/rg"), &rg).unwrap();
+    let host = env.host_bin("host", &[("rg", "#!/bin/sh\necho 'ripgrep 14.1.0'\n")]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("repair")
+        .arg(env.path())
+        .env("PATH", common::path_with(&[&host]))
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::canonicalize(&rg).unwrap(), host.join("rg"));
+}
+
+#[test]
+fn test_repair_refuses_directory_without_marker() {
+    let env = common::dummy_environment(&[]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("repair")
+        .arg(env.path())
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("is not an isoterm environment")
+    );
+}
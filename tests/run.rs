@@ -0,0 +1,154 @@
+mod common;
+
+use std::fs;
+use std::process::Command;
+use tempfile::tempdir;
+
+/// Creates an environment whose tools are dummy scripts. The dummy fish prints its
+/// arguments and exits with status 5, instead of starting a shell.
+fn create_environment() -> common::TestEnv {
+    common::create_environment(&[("fish", "#!/bin/sh\necho \"$*\"\nexit 5\n")], &[])
+}
+
+#[test]
+fn test_run_command_in_environment() {
+    let env = create_environment();
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("run")
+        .arg(env.path())
+        .args(["--", "sh", "-c"])
+        .arg("echo \"$XDG_CONFIG_HOME\"; command -v rg; exit 3")
+        .output()
+        .expect("Failed to execute isoterm");
+
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{0}/config\n{0}/bin/rg\n", env_dir.display())
+    );
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_shell_starts_configured_shell() {
+    let env = create_environment();
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("shell")
+        .arg(env.path())
+        .output()
+        .expect("Failed to execute isoterm");
+
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "-l -C source '{}/config/fish/config.fish'\n",
+            env_dir.display()
+        )
+    );
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn test_run_rejects_missing_environment() {
+    let dir = tempdir().expect("Failed to create temp dir");
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("run")
+        .arg(dir.path().join("missing"))
+        .args(["--", "true"])
+        .output()
+        .expect("Failed to execute isoterm");
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("is not an isoterm environment")
+    );
+}
+
+#[test]
+fn test_env_prints_activated_variables() {
+    let env = create_environment();
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("env")
+        .arg(env.path())
+        .args(["--format", "json"])
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(output.status.success());
+
+    let vars: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        vars["XDG_CONFIG_HOME"],
+        format!("{}/config", env_dir.display())
+    );
+    assert!(
+        vars["PATH"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("{}/bin:", env_dir.display()))
+    );
+}
+
+#[test]
+fn test_env_diff_skips_unchanged_variables() {
+    let env = create_environment();
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("env")
+        .arg(env.path())
+        .args(["--format", "dotenv", "--diff"])
+        .env("XDG_CONFIG_HOME", env_dir.join("config"))
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("XDG_CONFIG_HOME="), "{}", stdout);
+    assert!(
+        stdout.contains(&format!("XDG_DATA_HOME=\"{}/data\"\n", env_dir.display())),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_env_uses_recorded_activations() {
+    let env = create_environment();
+    let env_dir = fs::canonicalize(env.path()).unwrap();
+
+    // Stands in for a declared tool, which the registry knows nothing about without its
+    // tools file.
+    let state_path = env.path().join("isoterm.json");
+    let mut state: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap();
+    let activations = state["activations"].as_array_mut().unwrap();
+    assert!(!activations.is_empty());
+    activations.push(serde_json::json!({
+        "env_vars": [{ "name": "DECLARED_HOME", "path": "declared" }],
+        "path_dirs": ["declared/bin"],
+    }));
+    fs::write(&state_path, state.to_string()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_isoterm"))
+        .arg("env")
+        .arg(env.path())
+        .args(["--format", "json"])
+        .output()
+        .expect("Failed to execute isoterm");
+    assert!(output.status.success());
+
+    let vars: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        vars["DECLARED_HOME"],
+        format!("{}/declared", env_dir.display())
+    );
+    assert!(
+        vars["PATH"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("{0}/bin:{0}/declared/bin:", env_dir.display()))
+    );
+}